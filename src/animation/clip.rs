@@ -0,0 +1,155 @@
+use std::ops::{Add, Mul};
+
+use cgmath::{InnerSpace, Quaternion, Vector3};
+
+use crate::components::Pose;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    /// Holds each keyframe until the next one.
+    Step,
+    /// Hermite spline through the keyframes, with the tangents stored alongside them.
+    CubicSpline,
+}
+
+/// Values of an animated property at each keyframe. Cubic spline channels store three values per
+/// keyframe: the in-tangent, the value and the out-tangent.
+#[derive(Clone, Debug)]
+pub enum Keyframes {
+    Translation(Vec<Vector3<f32>>),
+    Rotation(Vec<Quaternion<f32>>),
+    Scale(Vec<Vector3<f32>>),
+    /// Morph target weights of the mesh of the node, all the targets of a keyframe together.
+    Weights(Vec<f32>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Channel {
+    /// Index of the animated node in the skeleton.
+    pub node: usize,
+    pub interpolation: Interpolation,
+    /// Keyframe times in seconds, in increasing order.
+    pub times: Vec<f32>,
+    pub keyframes: Keyframes,
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+    pub name: String,
+    pub channels: Vec<Channel>,
+}
+
+impl AnimationClip {
+    /// Time of the last keyframe, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.channels
+            .iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max)
+    }
+
+    /// Overwrites the properties the clip animates in `pose` with their values at `time`.
+    pub fn sample(&self, time: f32, pose: &mut Pose) {
+        for channel in &self.channels {
+            let Some(node) = pose.transforms.get_mut(channel.node) else {
+                continue;
+            };
+            if channel.times.is_empty() {
+                continue;
+            }
+
+            let (interpolation, times) = (channel.interpolation, &channel.times[..]);
+            match &channel.keyframes {
+                Keyframes::Translation(values) => {
+                    node.translation = sample(interpolation, times, time, |i| values[i]);
+                }
+                Keyframes::Rotation(values) => {
+                    node.rotation = sample(interpolation, times, time, |i| values[i]).normalize();
+                }
+                Keyframes::Scale(values) => {
+                    node.scale = sample(interpolation, times, time, |i| values[i]);
+                }
+                Keyframes::Weights(values) => {
+                    let Some(weights) = pose.weights.get_mut(channel.node) else {
+                        continue;
+                    };
+                    let values_per_target = match interpolation {
+                        Interpolation::CubicSpline => times.len() * 3,
+                        _ => times.len(),
+                    };
+                    let target_count = values.len() / values_per_target;
+
+                    *weights = (0..target_count)
+                        .map(|target| {
+                            sample(interpolation, times, time, |i| {
+                                values[i * target_count + target]
+                            })
+                        })
+                        .collect();
+                }
+            }
+        }
+    }
+}
+
+trait Keyframe: Copy + Add<Output = Self> + Mul<f32, Output = Self> {
+    fn lerp(self, other: Self, amount: f32) -> Self {
+        self * (1.0 - amount) + other * amount
+    }
+}
+
+impl Keyframe for f32 {}
+
+impl Keyframe for Vector3<f32> {}
+
+impl Keyframe for Quaternion<f32> {
+    // Takes the shortest path between the rotations
+    fn lerp(self, other: Self, amount: f32) -> Self {
+        let other = if self.dot(other) < 0.0 { -other } else { other };
+        self.nlerp(other, amount)
+    }
+}
+
+/// Samples the keyframes at `time`, where `values` returns the stored value at an index.
+fn sample<T: Keyframe>(
+    interpolation: Interpolation,
+    times: &[f32],
+    time: f32,
+    values: impl Fn(usize) -> T,
+) -> T {
+    let value = |keyframe: usize| match interpolation {
+        Interpolation::CubicSpline => values(keyframe * 3 + 1),
+        _ => values(keyframe),
+    };
+
+    let last = times.len() - 1;
+    if time <= times[0] {
+        return value(0);
+    }
+    if time >= times[last] {
+        return value(last);
+    }
+
+    let previous = times.partition_point(|&keyframe_time| keyframe_time <= time) - 1;
+    let next = previous + 1;
+    let delta = times[next] - times[previous];
+    let amount = (time - times[previous]) / delta;
+
+    match interpolation {
+        Interpolation::Step => value(previous),
+        Interpolation::Linear => value(previous).lerp(value(next), amount),
+        Interpolation::CubicSpline => {
+            let out_tangent = values(previous * 3 + 2) * delta;
+            let in_tangent = values(next * 3) * delta;
+
+            let t2 = amount * amount;
+            let t3 = t2 * amount;
+
+            value(previous) * (2.0 * t3 - 3.0 * t2 + 1.0)
+                + out_tangent * (t3 - 2.0 * t2 + amount)
+                + value(next) * (-2.0 * t3 + 3.0 * t2)
+                + in_tangent * (t3 - t2)
+        }
+    }
+}
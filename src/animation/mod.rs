@@ -0,0 +1,7 @@
+mod clip;
+mod player;
+
+pub use self::{
+    clip::{AnimationClip, Channel, Interpolation, Keyframes},
+    player::{AnimationPlayer, PlayingClip},
+};
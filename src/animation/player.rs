@@ -0,0 +1,178 @@
+use cgmath::{InnerSpace, Quaternion, Vector3, Zero};
+
+use super::AnimationClip;
+use crate::components::{NodeTransform, Pose, Skin};
+
+/// Clip being played, with its own time and share of the blended pose.
+#[derive(Clone, Copy, Debug)]
+pub struct PlayingClip {
+    /// Index of the clip in the animations of the model.
+    pub clip: usize,
+    /// Playback position in seconds.
+    pub time: f32,
+    pub speed: f32,
+    /// Whether the clip starts over when it ends, instead of holding its last pose.
+    pub looping: bool,
+    pub weight: f32,
+}
+
+impl PlayingClip {
+    fn new(clip: usize, weight: f32) -> Self {
+        Self {
+            clip,
+            time: 0.0,
+            speed: 1.0,
+            looping: true,
+            weight,
+        }
+    }
+}
+
+/// Plays and blends the animation clips of a model. The owner advances it with `update`, and
+/// the pass poses the skeleton of the entity with `pose`.
+#[derive(Clone, Debug, Default)]
+pub struct AnimationPlayer {
+    playing: Vec<PlayingClip>,
+    paused: bool,
+}
+
+impl AnimationPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Plays a clip from the start on its own, stopping the others.
+    pub fn play(&mut self, clip: usize) -> &mut PlayingClip {
+        self.playing.clear();
+        self.blend(clip, 1.0)
+    }
+
+    /// Plays a clip together with the ones already playing, mixed in by `weight`. Changes the
+    /// weight of the clip when it's already playing.
+    pub fn blend(&mut self, clip: usize, weight: f32) -> &mut PlayingClip {
+        let index = match self.playing.iter().position(|playing| playing.clip == clip) {
+            Some(index) => {
+                self.playing[index].weight = weight;
+                index
+            }
+            None => {
+                self.playing.push(PlayingClip::new(clip, weight));
+                self.playing.len() - 1
+            }
+        };
+
+        &mut self.playing[index]
+    }
+
+    pub fn stop(&mut self, clip: usize) {
+        self.playing.retain(|playing| playing.clip != clip);
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn playing(&self) -> &[PlayingClip] {
+        &self.playing
+    }
+
+    pub fn playing_mut(&mut self, clip: usize) -> Option<&mut PlayingClip> {
+        self.playing.iter_mut().find(|playing| playing.clip == clip)
+    }
+
+    /// Advances the playing clips by `delta` seconds.
+    pub fn update(&mut self, delta: f32, clips: &[AnimationClip]) {
+        if self.paused {
+            return;
+        }
+
+        for playing in &mut self.playing {
+            let Some(clip) = clips.get(playing.clip) else {
+                continue;
+            };
+
+            let duration = clip.duration();
+            playing.time += delta * playing.speed;
+            playing.time = if playing.looping && duration > 0.0 {
+                playing.time.rem_euclid(duration)
+            } else {
+                playing.time.clamp(0.0, duration)
+            };
+        }
+    }
+
+    /// Local transforms and morph target weights of the skeleton nodes, blending the playing clips
+    /// by weight. Nodes no clip animates keep their rest state.
+    pub fn pose(&self, clips: &[AnimationClip], skin: &Skin) -> Pose {
+        let rest = skin.rest_pose();
+
+        let layers = self
+            .playing
+            .iter()
+            .filter(|playing| playing.weight > 0.0)
+            .filter_map(|playing| Some((clips.get(playing.clip)?, playing)))
+            .collect::<Vec<_>>();
+        let total_weight = layers
+            .iter()
+            .map(|(_, playing)| playing.weight)
+            .sum::<f32>();
+
+        if layers.is_empty() {
+            return rest;
+        }
+
+        let node_count = rest.transforms.len();
+        let mut translations = vec![Vector3::zero(); node_count];
+        let mut rotations = vec![Quaternion::zero(); node_count];
+        let mut scales = vec![Vector3::zero(); node_count];
+        let mut morph_weights = rest
+            .weights
+            .iter()
+            .map(|weights| vec![0.0; weights.len()])
+            .collect::<Vec<_>>();
+
+        for (clip, playing) in layers {
+            let mut pose = rest.clone();
+            clip.sample(playing.time, &mut pose);
+
+            let weight = playing.weight / total_weight;
+            for (i, node) in pose.transforms.iter().enumerate() {
+                translations[i] += node.translation * weight;
+                scales[i] += node.scale * weight;
+
+                // Keep the rotations in the same hemisphere so they don't cancel out
+                let rotation = if rotations[i].dot(node.rotation) < 0.0 {
+                    -node.rotation
+                } else {
+                    node.rotation
+                };
+                rotations[i] += rotation * weight;
+
+                for (sum, value) in morph_weights[i].iter_mut().zip(&pose.weights[i]) {
+                    *sum += value * weight;
+                }
+            }
+        }
+
+        let transforms = (0..node_count)
+            .map(|i| NodeTransform {
+                translation: translations[i],
+                rotation: rotations[i].normalize(),
+                scale: scales[i],
+            })
+            .collect();
+
+        Pose {
+            transforms,
+            weights: morph_weights,
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+/// Shared, reference counted asset. Cloning a handle is cheap, and the asset with its GPU
+/// resources is freed when the last handle is dropped. Reloading replaces the asset behind every
+/// handle to it, so users take a snapshot with `get` when they need it. Handles are equal when
+/// they point to the same asset.
+pub struct Handle<T>(pub(super) Arc<Slot<T>>);
+
+pub(super) struct Slot<T> {
+    asset: RwLock<Arc<T>>,
+    version: AtomicU64,
+}
+
+impl<T> Handle<T> {
+    /// Wraps an asset that isn't managed by an `Assets` store.
+    pub fn new(asset: T) -> Self {
+        Self(Arc::new(Slot {
+            asset: RwLock::new(Arc::new(asset)),
+            version: AtomicU64::new(0),
+        }))
+    }
+
+    /// Current version of the asset, which stays alive while the snapshot is kept even if the
+    /// asset is reloaded meanwhile.
+    pub fn get(&self) -> Arc<T> {
+        self.0.asset.read().unwrap().clone()
+    }
+
+    /// Number of times the asset has been replaced.
+    pub fn version(&self) -> u64 {
+        self.0.version.load(Ordering::Acquire)
+    }
+
+    /// Replaces the asset seen through every handle to it.
+    pub fn replace(&self, asset: T) {
+        *self.0.asset.write().unwrap() = Arc::new(asset);
+        self.0.version.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Address identifying the asset while any handle to it is alive. It stays the same when
+    /// the asset is reloaded.
+    pub fn as_ptr(&self) -> *const () {
+        Arc::as_ptr(&self.0).cast()
+    }
+
+    /// Number of handles to the asset.
+    pub fn count(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> From<T> for Handle<T> {
+    fn from(asset: T) -> Self {
+        Self::new(asset)
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ptr().hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.as_ptr()).finish()
+    }
+}
@@ -0,0 +1,286 @@
+mod handle;
+mod roots;
+mod watcher;
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, Weak},
+};
+
+use crate::{components::Model, texture::Texture, utils};
+
+use self::{handle::Slot, roots::recording_dependencies, watcher::Watcher};
+
+pub(crate) use self::roots::crate_directory;
+pub use self::{
+    handle::Handle,
+    roots::{
+        asset_root_from_args, asset_roots, resolve_asset, set_asset_roots, ASSET_ROOTS_ENV,
+        ASSET_ROOT_FLAG,
+    },
+};
+
+/// Loads models and textures once per path and hands out shared handles to them. The store only
+/// keeps weak references, so assets are freed when nothing uses them anymore and loaded again
+/// the next time they're asked for.
+///
+/// Once `watch` is called, the files of the loaded assets are watched, and `reload_changed`
+/// replaces the assets whose files changed behind their existing handles.
+#[derive(Default)]
+pub struct Assets {
+    models: Store<Model>,
+    textures: Store<Texture>,
+    shaders: Store<String>,
+    watcher: Mutex<Option<Watcher>>,
+}
+
+impl Assets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads an OBJ, glTF or GLB model like `load_model`, sharing the textures it uses with the
+    /// other models of the store.
+    pub fn load_model(
+        &self,
+        file_name: &str,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> anyhow::Result<Handle<Model>> {
+        let file = file_name.to_string();
+        self.models.get_or_load(
+            file_name,
+            || utils::load_model_with(file_name, device, queue, self),
+            Arc::new(move |assets, device, queue| {
+                utils::load_model_with(&file, device, queue, assets)
+            }),
+        )
+    }
+
+    pub fn load_texture(
+        &self,
+        file_name: &str,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> anyhow::Result<Handle<Texture>> {
+        let file = file_name.to_string();
+        self.textures.get_or_load(
+            file_name,
+            || utils::load_texture(file_name, device, queue),
+            Arc::new(move |_, device, queue| utils::load_texture(&file, device, queue)),
+        )
+    }
+
+    /// Like `load_texture`, for images that hold data rather than colors. It's a different
+    /// texture from the one `load_texture` gives for the same file.
+    pub fn load_linear_texture(
+        &self,
+        file_name: &str,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> anyhow::Result<Handle<Texture>> {
+        let file = file_name.to_string();
+        self.textures.get_or_load(
+            &format!("{}#linear", file_name),
+            || utils::load_linear_texture(file_name, device, queue),
+            Arc::new(move |_, device, queue| utils::load_linear_texture(&file, device, queue)),
+        )
+    }
+
+    /// Source of a WGSL shader, reloaded like the other assets so pipelines can be rebuilt from
+    /// it.
+    pub fn load_shader(&self, file_name: &str) -> anyhow::Result<Handle<String>> {
+        let file = file_name.to_string();
+        self.shaders.get_or_load(
+            file_name,
+            || utils::load_string(file_name),
+            Arc::new(move |_, _, _| utils::load_string(&file)),
+        )
+    }
+
+    /// Texture stored under `key`, created with `load` when it isn't loaded and with `reload`
+    /// when its files change. For textures that don't come from a file of their own, like the
+    /// ones embedded in glTF files.
+    pub(crate) fn texture_or_load(
+        &self,
+        key: &str,
+        load: impl FnOnce() -> anyhow::Result<Texture>,
+        reload: Reload<Texture>,
+    ) -> anyhow::Result<Handle<Texture>> {
+        self.textures.get_or_load(key, load, reload)
+    }
+
+    /// Number of models of the store still in use.
+    pub fn model_count(&self) -> usize {
+        self.models.count()
+    }
+
+    /// Number of textures of the store still in use.
+    pub fn texture_count(&self) -> usize {
+        self.textures.count()
+    }
+
+    /// Starts watching the asset roots for changes, picked up by `reload_changed`.
+    pub fn watch(&self) -> anyhow::Result<()> {
+        asset_roots()
+            .iter()
+            .filter(|root| root.is_dir())
+            .try_for_each(|root| self.watch_directory(root))
+    }
+
+    /// Watches a directory other than the asset roots, like one with assets loaded from
+    /// absolute paths.
+    pub fn watch_directory(&self, directory: &Path) -> anyhow::Result<()> {
+        let mut watcher = self.watcher.lock().unwrap();
+        match &mut *watcher {
+            Some(watcher) => watcher.watch(directory),
+            None => {
+                let mut new_watcher = Watcher::new()?;
+                new_watcher.watch(directory)?;
+                *watcher = Some(new_watcher);
+                Ok(())
+            }
+        }
+    }
+
+    /// Reloads the assets whose files changed since the last call, and returns the changed
+    /// files. Does nothing until `watch` is called.
+    pub fn reload_changed(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<PathBuf> {
+        let changed = match &*self.watcher.lock().unwrap() {
+            Some(watcher) => watcher.changed_files(),
+            None => return Vec::new(),
+        };
+        if !changed.is_empty() {
+            self.reload_files(&changed, device, queue);
+        }
+
+        changed
+    }
+
+    /// Reloads the assets that use any of `files`. An asset that fails to load keeps its
+    /// previous version, and the error is logged.
+    pub fn reload_files(&self, files: &[PathBuf], device: &wgpu::Device, queue: &wgpu::Queue) {
+        let files = files
+            .iter()
+            .map(|file| file.canonicalize().unwrap_or_else(|_| file.clone()))
+            .collect::<HashSet<_>>();
+
+        // Textures first, so reloaded models pick up the new ones
+        self.textures.reload(&files, self, device, queue);
+        self.models.reload(&files, self, device, queue);
+        self.shaders.reload(&files, self, device, queue);
+    }
+}
+
+pub(crate) type Reload<T> =
+    Arc<dyn Fn(&Assets, &wgpu::Device, &wgpu::Queue) -> anyhow::Result<T> + Send + Sync>;
+
+struct Entry<T> {
+    slot: Weak<Slot<T>>,
+    /// Files read when the asset was last loaded.
+    files: Vec<PathBuf>,
+    reload: Reload<T>,
+}
+
+struct Store<T> {
+    loaded: Mutex<HashMap<String, Entry<T>>>,
+}
+
+impl<T> Default for Store<T> {
+    fn default() -> Self {
+        Self {
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T> Store<T> {
+    fn get_or_load(
+        &self,
+        key: &str,
+        load: impl FnOnce() -> anyhow::Result<T>,
+        reload: Reload<T>,
+    ) -> anyhow::Result<Handle<T>> {
+        let loaded = self
+            .loaded
+            .lock()
+            .unwrap()
+            .get(key)
+            .and_then(|entry| entry.slot.upgrade());
+        if let Some(slot) = loaded {
+            return Ok(Handle(slot));
+        }
+
+        // The lock isn't held while loading, models load their textures through the same store
+        let (asset, files) = recording_dependencies(load);
+        let asset = asset?;
+
+        let mut loaded = self.loaded.lock().unwrap();
+        // Another thread may have loaded the same key meanwhile, keep its handle so both share it
+        if let Some(slot) = loaded.get(key).and_then(|entry| entry.slot.upgrade()) {
+            return Ok(Handle(slot));
+        }
+
+        let handle = Handle::new(asset);
+        loaded.retain(|_, entry| entry.slot.strong_count() > 0);
+        loaded.insert(
+            key.to_string(),
+            Entry {
+                slot: Arc::downgrade(&handle.0),
+                files,
+                reload,
+            },
+        );
+
+        Ok(handle)
+    }
+
+    fn reload(
+        &self,
+        files: &HashSet<PathBuf>,
+        assets: &Assets,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let stale = self
+            .loaded
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, entry)| entry.files.iter().any(|file| files.contains(file)))
+            .filter_map(|(key, entry)| {
+                let slot = entry.slot.upgrade()?;
+                Some((key.clone(), Handle(slot), entry.reload.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        for (key, handle, reload) in stale {
+            let (asset, files) = recording_dependencies(|| reload(assets, device, queue));
+            match asset {
+                Ok(asset) => {
+                    handle.replace(asset);
+                    if let Some(entry) = self.loaded.lock().unwrap().get_mut(&key) {
+                        entry.files = files;
+                    }
+                    log::info!("Reloaded asset {:?}", key);
+                }
+                Err(error) => {
+                    log::error!(
+                        "Couldn't reload asset {:?}, keeping it as is: {:#}",
+                        key,
+                        error
+                    )
+                }
+            }
+        }
+    }
+
+    fn count(&self) -> usize {
+        let loaded = self.loaded.lock().unwrap();
+        loaded
+            .values()
+            .filter(|entry| entry.slot.strong_count() > 0)
+            .count()
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    cell::RefCell,
+    env,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use anyhow::Context;
+
+/// Environment variable listing asset roots, separated like `PATH`.
+pub const ASSET_ROOTS_ENV: &str = "WGPU_RENDERER_ASSETS";
+
+/// Command line flag giving an asset root, as `--assets <dir>` or `--assets=<dir>`.
+pub const ASSET_ROOT_FLAG: &str = "--assets";
+
+/// Roots set by the application, searched before the default ones.
+static ROOTS: RwLock<Vec<PathBuf>> = RwLock::new(Vec::new());
+
+thread_local! {
+    /// Files resolved by the asset being loaded on this thread, which reload it when they change.
+    static DEPENDENCIES: RefCell<Option<Vec<PathBuf>>> = const { RefCell::new(None) };
+}
+
+/// Replaces the roots set by the application, like the one given with `ASSET_ROOT_FLAG`.
+pub fn set_asset_roots<P: Into<PathBuf>>(roots: impl IntoIterator<Item = P>) {
+    *ROOTS.write().unwrap() = roots.into_iter().map(Into::into).collect();
+}
+
+/// Directories assets are looked up in, in order: the roots set with `set_asset_roots`, the ones
+/// in `ASSET_ROOTS_ENV`, `res` next to the executable, `res` in the crate directory when run
+/// through Cargo and `res` in the working directory.
+pub fn asset_roots() -> Vec<PathBuf> {
+    let mut roots = ROOTS.read().unwrap().clone();
+
+    if let Some(paths) = env::var_os(ASSET_ROOTS_ENV) {
+        roots.extend(env::split_paths(&paths).filter(|path| !path.as_os_str().is_empty()));
+    }
+    if let Some(directory) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        roots.push(directory.join("res"));
+    }
+    if let Some(directory) = crate_directory() {
+        roots.push(directory.join("res"));
+    }
+    roots.push(PathBuf::from("res"));
+
+    roots
+}
+
+/// Directory of the crate when run through Cargo, which sets `CARGO_MANIFEST_DIR` for `cargo run`
+/// and `cargo test`. Read when running rather than when building, so binaries don't point at the
+/// build machine.
+pub(crate) fn crate_directory() -> Option<PathBuf> {
+    env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from)
+}
+
+/// Root given with `ASSET_ROOT_FLAG` in `args`, if any.
+pub fn asset_root_from_args(args: impl IntoIterator<Item = String>) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == ASSET_ROOT_FLAG {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(root) = arg
+            .strip_prefix(ASSET_ROOT_FLAG)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(PathBuf::from(root));
+        }
+    }
+
+    None
+}
+
+/// Path of the asset in the first root that has it. Absolute paths are used as they are.
+pub fn resolve_asset(file_name: &str) -> anyhow::Result<PathBuf> {
+    let path = Path::new(file_name);
+    if path.is_absolute() {
+        record_dependency(path);
+        return Ok(path.to_path_buf());
+    }
+
+    let roots = asset_roots();
+    let resolved = roots
+        .iter()
+        .map(|root| root.join(path))
+        .find(|candidate| candidate.is_file());
+    if let Some(resolved) = &resolved {
+        record_dependency(resolved);
+    }
+
+    resolved.with_context(|| {
+        let searched = roots
+            .iter()
+            .map(|root| root.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "Couldn't find asset {:?} in any of [{}]. Set {} or pass {} <dir> to add a root",
+            file_name, searched, ASSET_ROOTS_ENV, ASSET_ROOT_FLAG
+        )
+    })
+}
+
+/// Runs `load`, returning the files it resolved as well. Loads nested in it record their own.
+pub(super) fn recording_dependencies<R>(load: impl FnOnce() -> R) -> (R, Vec<PathBuf>) {
+    let outer = DEPENDENCIES.with(|dependencies| dependencies.replace(Some(Vec::new())));
+    let result = load();
+    let recorded = DEPENDENCIES.with(|dependencies| dependencies.replace(outer));
+
+    (result, recorded.unwrap_or_default())
+}
+
+fn record_dependency(path: &Path) {
+    DEPENDENCIES.with(|dependencies| {
+        if let Some(dependencies) = dependencies.borrow_mut().as_mut() {
+            // Canonical, to match the paths the watcher reports
+            dependencies.push(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+        }
+    });
+}
@@ -0,0 +1,57 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+use notify::{EventKind, RecursiveMode, Watcher as _};
+
+/// Watches directories, collecting the files written to or created in them.
+pub(super) struct Watcher {
+    watcher: notify::RecommendedWatcher,
+    watched: HashSet<PathBuf>,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl Watcher {
+    pub fn new() -> anyhow::Result<Self> {
+        let (sender, events) = mpsc::channel();
+
+        Ok(Self {
+            watcher: notify::recommended_watcher(sender)?,
+            watched: HashSet::new(),
+            events,
+        })
+    }
+
+    /// Watches `directory` and the ones in it, unless it's already watched.
+    pub fn watch(&mut self, directory: &Path) -> anyhow::Result<()> {
+        let directory = directory.canonicalize()?;
+        if self.watched.insert(directory.clone()) {
+            self.watcher.watch(&directory, RecursiveMode::Recursive)?;
+            log::info!("Watching assets in {:?}", directory);
+        }
+
+        Ok(())
+    }
+
+    /// Files changed since the last call, each once.
+    pub fn changed_files(&self) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+
+        self.events
+            .try_iter()
+            .filter_map(|event| match event {
+                Ok(event) => Some(event),
+                Err(error) => {
+                    log::error!("Asset watcher error: {}", error);
+                    None
+                }
+            })
+            .filter(|event| matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)))
+            .flat_map(|event| event.paths)
+            .map(|path| path.canonicalize().unwrap_or(path))
+            .filter(|path| seen.insert(path.clone()))
+            .collect()
+    }
+}
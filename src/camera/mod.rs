@@ -1,4 +1,4 @@
-use cgmath::{perspective, Deg, InnerSpace, Matrix4, Point3, Rad, Vector3};
+use cgmath::{perspective, Deg, InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Vector3, Vector4};
 
 mod camera_controller;
 
@@ -40,17 +40,50 @@ impl Camera {
     }
 
     pub fn get_view_projection_matrix(&self) -> Matrix4<f32> {
+        self.get_projection_matrix(self.znear, self.zfar) * self.get_view_matrix()
+    }
+
+    pub fn get_view_matrix(&self) -> Matrix4<f32> {
         let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
         let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
 
-        let view_matrix = Matrix4::look_to_rh(
+        Matrix4::look_to_rh(
             self.position,
             Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize(),
             Vector3::unit_y(),
-        );
-        let projection_matrix = perspective(Deg(self.fovy), self.aspect, self.znear, self.zfar);
+        )
+    }
+
+    /// Projection between arbitrary near and far planes, with the camera field of view and aspect.
+    pub fn get_projection_matrix(&self, znear: f32, zfar: f32) -> Matrix4<f32> {
+        OPENGL_TO_WGPU_MATRIX * perspective(Deg(self.fovy), self.aspect, znear, zfar)
+    }
+
+    /// World space corners of the view frustum between `znear` and `zfar`, near plane first.
+    /// `None` when the view projection between them can't be inverted.
+    pub fn frustum_corners(&self, znear: f32, zfar: f32) -> Option<[Point3<f32>; 8]> {
+        let inverse =
+            (self.get_projection_matrix(znear, zfar) * self.get_view_matrix()).invert()?;
+
+        let mut corners = [Point3::new(0.0, 0.0, 0.0); 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let x = if i & 1 == 0 { -1.0 } else { 1.0 };
+            let y = if i & 2 == 0 { -1.0 } else { 1.0 };
+            let z = if i & 4 == 0 { 0.0 } else { 1.0 };
+
+            let point = inverse * Vector4::new(x, y, z, 1.0);
+            *corner = Point3::from_homogeneous(point);
+        }
+
+        Some(corners)
+    }
+
+    pub fn znear(&self) -> f32 {
+        self.znear
+    }
 
-        OPENGL_TO_WGPU_MATRIX * projection_matrix * view_matrix
+    pub fn zfar(&self) -> f32 {
+        self.zfar
     }
 
     pub fn get_position(&self) -> cgmath::Point3<f32> {
@@ -66,6 +99,6 @@ impl Camera {
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     1.0, 0.0, 0.0, 0.0,
     0.0, 1.0, 0.0, 0.0,
-    0.0, 0.0, 0.5, 0.5,
-    0.0, 0.0, 0.0, 1.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
 );
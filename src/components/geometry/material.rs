@@ -1,6 +1,66 @@
-use crate::texture;
+use crate::{assets::Handle, texture::Texture};
 
+/// Metallic-roughness material, as in glTF. Each texture is multiplied by its factor, and the
+/// surface uses the factor alone when the texture is missing.
 pub struct Material {
     pub name: String,
-    pub texture: texture::Texture,
+    /// Base color texture, shared with the other materials that use the same image.
+    pub texture: Handle<Texture>,
+    /// Linear base color, matching `Kd` in MTL files.
+    pub base_color: [f32; 3],
+    /// Share of the light behind the surface it blocks, matching `d` in MTL files.
+    pub opacity: f32,
+    pub metallic: f32,
+    /// Perceptual roughness, squared into the width of the microfacet distribution.
+    pub roughness: f32,
+    /// Roughness in the green channel and metalness in the blue one, loaded as linear data.
+    pub metallic_roughness_texture: Option<Handle<Texture>>,
+    /// Tangent space normals, loaded as linear data, with their `x` and `y` scaled by
+    /// `normal_scale`.
+    pub normal_texture: Option<Handle<Texture>>,
+    pub normal_scale: f32,
+    /// Ambient occlusion in the red channel, loaded as linear data. `occlusion_strength` is how
+    /// much of it applies.
+    pub occlusion_texture: Option<Handle<Texture>>,
+    pub occlusion_strength: f32,
+    /// Light the surface gives off, matching `Ke` in MTL files.
+    pub emissive: [f32; 3],
+    pub emissive_texture: Option<Handle<Texture>>,
+}
+
+impl Material {
+    pub const DEFAULT_BASE_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+    pub const DEFAULT_OPACITY: f32 = 1.0;
+    pub const DEFAULT_METALLIC: f32 = 0.0;
+    pub const DEFAULT_ROUGHNESS: f32 = 0.5;
+    pub const DEFAULT_EMISSIVE: [f32; 3] = [0.0, 0.0, 0.0];
+
+    pub fn new(name: &str, texture: impl Into<Handle<Texture>>) -> Self {
+        Self {
+            name: name.to_string(),
+            texture: texture.into(),
+            base_color: Self::DEFAULT_BASE_COLOR,
+            opacity: Self::DEFAULT_OPACITY,
+            metallic: Self::DEFAULT_METALLIC,
+            roughness: Self::DEFAULT_ROUGHNESS,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive: Self::DEFAULT_EMISSIVE,
+            emissive_texture: None,
+        }
+    }
+
+    /// Roughness of a Phong specular exponent, like `Ns` in MTL files, inverting the usual
+    /// `2 / alpha^2 - 2` mapping to a microfacet width.
+    pub fn roughness_from_shininess(shininess: f32) -> f32 {
+        (2.0 / (shininess.max(0.0) + 2.0)).powf(0.25)
+    }
+
+    /// Whether the surface is blended over what's behind it.
+    pub fn is_transparent(&self) -> bool {
+        self.opacity < 1.0
+    }
 }
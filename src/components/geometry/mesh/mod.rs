@@ -5,4 +5,22 @@ pub struct Mesh {
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub index_count: usize,
+    pub morph_targets: Option<MorphTargets>,
+}
+
+/// Blend shapes of a mesh, displaced in the vertex shader by the weight of each target.
+pub struct MorphTargets {
+    /// Position, normal and tangent deltas of every vertex as `vec4`s, one target after the other.
+    pub buffer: wgpu::Buffer,
+    pub count: usize,
+    pub vertex_count: usize,
+    /// Skeleton node whose animated weights drive the targets.
+    pub node: Option<usize>,
+    /// Weights used when neither the entity nor an animation sets them.
+    pub default_weights: Vec<f32>,
+}
+
+impl MorphTargets {
+    /// Number of `vec4`s each vertex of a target takes in `buffer`.
+    pub const DELTAS_PER_VERTEX: usize = 3;
 }
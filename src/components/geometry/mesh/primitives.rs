@@ -6,35 +6,35 @@ fn create_cube_data() -> ([Vertex; 24], [u32; 36]) {
     #[rustfmt::skip]
     let vertices = [
 
-        Vertex {position: [-1.0, -1.0,  1.0], uv: [0.0, 1.0] },
-        Vertex {position: [ 1.0, -1.0,  1.0], uv: [1.0, 1.0] },
-        Vertex {position: [ 1.0,  1.0,  1.0], uv: [1.0, 0.0] },
-        Vertex {position: [-1.0,  1.0,  1.0], uv: [0.0, 0.0] },
-
-        Vertex {position: [-1.0,  1.0, -1.0], uv: [0.0, 1.0] },
-        Vertex {position: [ 1.0,  1.0, -1.0], uv: [1.0, 1.0] },
-        Vertex {position: [ 1.0, -1.0, -1.0], uv: [1.0, 0.0] },
-        Vertex {position: [-1.0, -1.0, -1.0], uv: [0.0, 0.0] },
-
-        Vertex {position: [ 1.0, -1.0, -1.0], uv: [0.0, 1.0] },
-        Vertex {position: [ 1.0,  1.0, -1.0], uv: [1.0, 1.0] },
-        Vertex {position: [ 1.0,  1.0,  1.0], uv: [1.0, 0.0] },
-        Vertex {position: [ 1.0, -1.0,  1.0], uv: [0.0, 0.0] },
-
-        Vertex {position: [-1.0, -1.0,  1.0], uv: [0.0, 1.0] },
-        Vertex {position: [-1.0,  1.0,  1.0], uv: [1.0, 1.0] },
-        Vertex {position: [-1.0,  1.0, -1.0], uv: [1.0, 0.0] },
-        Vertex {position: [-1.0, -1.0, -1.0], uv: [0.0, 0.0] },
-
-        Vertex {position: [ 1.0,  1.0, -1.0], uv: [0.0, 1.0] },
-        Vertex {position: [-1.0,  1.0, -1.0], uv: [1.0, 1.0] },
-        Vertex {position: [-1.0,  1.0,  1.0], uv: [1.0, 0.0] },
-        Vertex {position: [ 1.0,  1.0,  1.0], uv: [0.0, 0.0] },
-
-        Vertex {position: [ 1.0, -1.0,  1.0], uv: [0.0, 1.0] },
-        Vertex {position: [-1.0, -1.0,  1.0], uv: [1.0, 1.0] },
-        Vertex {position: [-1.0, -1.0, -1.0], uv: [1.0, 0.0] },
-        Vertex {position: [ 1.0, -1.0, -1.0], uv: [0.0, 0.0] },
+        Vertex {position: [-1.0, -1.0,  1.0], uv: [0.0, 1.0], normal: [ 0.0,  0.0,  1.0], tangent: [ 1.0,  0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [ 1.0, -1.0,  1.0], uv: [1.0, 1.0], normal: [ 0.0,  0.0,  1.0], tangent: [ 1.0,  0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [ 1.0,  1.0,  1.0], uv: [1.0, 0.0], normal: [ 0.0,  0.0,  1.0], tangent: [ 1.0,  0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [-1.0,  1.0,  1.0], uv: [0.0, 0.0], normal: [ 0.0,  0.0,  1.0], tangent: [ 1.0,  0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+
+        Vertex {position: [-1.0,  1.0, -1.0], uv: [0.0, 1.0], normal: [ 0.0,  0.0, -1.0], tangent: [ 1.0, -0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [ 1.0,  1.0, -1.0], uv: [1.0, 1.0], normal: [ 0.0,  0.0, -1.0], tangent: [ 1.0, -0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [ 1.0, -1.0, -1.0], uv: [1.0, 0.0], normal: [ 0.0,  0.0, -1.0], tangent: [ 1.0, -0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [-1.0, -1.0, -1.0], uv: [0.0, 0.0], normal: [ 0.0,  0.0, -1.0], tangent: [ 1.0, -0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+
+        Vertex {position: [ 1.0, -1.0, -1.0], uv: [0.0, 1.0], normal: [ 1.0,  0.0,  0.0], tangent: [ 0.0,  1.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [ 1.0,  1.0, -1.0], uv: [1.0, 1.0], normal: [ 1.0,  0.0,  0.0], tangent: [ 0.0,  1.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [ 1.0,  1.0,  1.0], uv: [1.0, 0.0], normal: [ 1.0,  0.0,  0.0], tangent: [ 0.0,  1.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [ 1.0, -1.0,  1.0], uv: [0.0, 0.0], normal: [ 1.0,  0.0,  0.0], tangent: [ 0.0,  1.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+
+        Vertex {position: [-1.0, -1.0,  1.0], uv: [0.0, 1.0], normal: [-1.0,  0.0,  0.0], tangent: [ 0.0,  1.0, -0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [-1.0,  1.0,  1.0], uv: [1.0, 1.0], normal: [-1.0,  0.0,  0.0], tangent: [ 0.0,  1.0, -0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [-1.0,  1.0, -1.0], uv: [1.0, 0.0], normal: [-1.0,  0.0,  0.0], tangent: [ 0.0,  1.0, -0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [-1.0, -1.0, -1.0], uv: [0.0, 0.0], normal: [-1.0,  0.0,  0.0], tangent: [ 0.0,  1.0, -0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+
+        Vertex {position: [ 1.0,  1.0, -1.0], uv: [0.0, 1.0], normal: [ 0.0,  1.0,  0.0], tangent: [-1.0,  0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [-1.0,  1.0, -1.0], uv: [1.0, 1.0], normal: [ 0.0,  1.0,  0.0], tangent: [-1.0,  0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [-1.0,  1.0,  1.0], uv: [1.0, 0.0], normal: [ 0.0,  1.0,  0.0], tangent: [-1.0,  0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [ 1.0,  1.0,  1.0], uv: [0.0, 0.0], normal: [ 0.0,  1.0,  0.0], tangent: [-1.0,  0.0,  0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+
+        Vertex {position: [ 1.0, -1.0,  1.0], uv: [0.0, 1.0], normal: [ 0.0, -1.0,  0.0], tangent: [-1.0,  0.0, -0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [-1.0, -1.0,  1.0], uv: [1.0, 1.0], normal: [ 0.0, -1.0,  0.0], tangent: [-1.0,  0.0, -0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [-1.0, -1.0, -1.0], uv: [1.0, 0.0], normal: [ 0.0, -1.0,  0.0], tangent: [-1.0,  0.0, -0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
+        Vertex {position: [ 1.0, -1.0, -1.0], uv: [0.0, 0.0], normal: [ 0.0, -1.0,  0.0], tangent: [-1.0,  0.0, -0.0, -1.0], joints: [0; 4], weights: [0.0; 4] },
     ];
 
     let indices = [
@@ -76,6 +76,7 @@ impl super::Mesh {
             vertex_buffer,
             index_buffer,
             index_count: indices.len(),
+            morph_targets: None,
         })
     }
 }
@@ -2,4 +2,8 @@ mod material;
 mod mesh;
 mod vertex;
 
-pub use self::{material::Material, mesh::Mesh, vertex::Vertex};
+pub use self::{
+    material::Material,
+    mesh::{Mesh, MorphTargets},
+    vertex::Vertex,
+};
@@ -7,6 +7,12 @@ use bytemuck::{Pod, Zeroable};
 pub struct Vertex {
     pub position: [f32; 3],
     pub uv: [f32; 2],
+    pub normal: [f32; 3],
+    /// Tangent in `xyz`, pointing along increasing `u`, and handedness of the bitangent in `w`.
+    pub tangent: [f32; 4],
+    /// Skin joints influencing the vertex, used when the weights add up to more than zero.
+    pub joints: [u16; 4],
+    pub weights: [f32; 4],
 }
 
 impl Vertex {
@@ -25,6 +31,26 @@ impl Vertex {
                     offset: 3 * size_of::<f32>() as u64,
                     shader_location: 1,
                 },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x3,
+                    offset: 5 * size_of::<f32>() as u64,
+                    shader_location: 2,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 8 * size_of::<f32>() as u64,
+                    shader_location: 3,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Uint16x4,
+                    offset: 12 * size_of::<f32>() as u64,
+                    shader_location: 4,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 12 * size_of::<f32>() as u64 + 4 * size_of::<u16>() as u64,
+                    shader_location: 5,
+                },
             ],
         }
     }
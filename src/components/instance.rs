@@ -0,0 +1,61 @@
+use std::mem::size_of;
+
+use bytemuck::{Pod, Zeroable};
+
+use super::{transform::normal_matrix, NormalMatrixRaw, Transform, TransformRaw};
+
+/// Copy of the model of an entity, placed relative to the entity transform.
+pub struct Instance {
+    pub transform: Transform,
+    /// Multiplies the colors of the materials of the model.
+    pub tint: [f32; 3],
+}
+
+impl Instance {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            transform,
+            tint: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// Per-instance vertex data: the model matrix, its normal matrix and the tint.
+#[repr(C)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+pub struct InstanceRaw {
+    pub m_matrix: TransformRaw,
+    pub normal_matrix: NormalMatrixRaw,
+    pub tint: [f32; 4],
+}
+
+impl InstanceRaw {
+    pub fn new(m_matrix: cgmath::Matrix4<f32>, tint: [f32; 3]) -> Self {
+        let [r, g, b] = tint;
+
+        Self {
+            m_matrix: m_matrix.into(),
+            normal_matrix: normal_matrix(&m_matrix),
+            tint: [r, g, b, 1.0],
+        }
+    }
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+            6 => Float32x4,
+            7 => Float32x4,
+            8 => Float32x4,
+            9 => Float32x4,
+            10 => Float32x4,
+            11 => Float32x4,
+            12 => Float32x4,
+            13 => Float32x4,
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<InstanceRaw>() as u64,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use cgmath::Deg;
+
+/// Distance falloff of point and spot lights: `1 / (constant + linear * d + quadratic * d^2)`.
+#[derive(Clone, Copy, Debug)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Self {
+            constant: 1.0,
+            linear: 0.09,
+            quadratic: 0.032,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LightKind {
+    Directional {
+        direction: [f32; 3],
+    },
+    Point {
+        attenuation: Attenuation,
+    },
+    Spot {
+        direction: [f32; 3],
+        inner_cutoff: Deg<f32>,
+        outer_cutoff: Deg<f32>,
+        attenuation: Attenuation,
+    },
+}
+
+/// Light source attached to an entity. Point and spot lights are placed at the entity position.
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Whether the light renders a shadow map. Only directional and point lights cast shadows,
+    /// and only directional lights do by default.
+    pub cast_shadows: bool,
+}
+
+impl Light {
+    pub fn directional(direction: [f32; 3]) -> Self {
+        Self::new(LightKind::Directional { direction })
+    }
+
+    pub fn point(attenuation: Attenuation) -> Self {
+        Self::new(LightKind::Point { attenuation })
+    }
+
+    pub fn spot(
+        direction: [f32; 3],
+        inner_cutoff: Deg<f32>,
+        outer_cutoff: Deg<f32>,
+        attenuation: Attenuation,
+    ) -> Self {
+        Self::new(LightKind::Spot {
+            direction,
+            inner_cutoff,
+            outer_cutoff,
+            attenuation,
+        })
+    }
+
+    fn new(kind: LightKind) -> Self {
+        Self {
+            kind,
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            cast_shadows: matches!(kind, LightKind::Directional { .. }),
+        }
+    }
+}
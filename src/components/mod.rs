@@ -1,9 +1,15 @@
 mod geometry;
+mod instance;
+mod light;
 mod model;
+mod skin;
 mod transform;
 
 pub use self::{
-    geometry::{Material, Mesh, Vertex},
+    geometry::{Material, Mesh, MorphTargets, Vertex},
+    instance::{Instance, InstanceRaw},
+    light::{Attenuation, Light, LightKind},
     model::Model,
-    transform::{Position, Rotation, Scale, Transform, TransformRaw},
+    skin::{Joint, NodeTransform, Pose, SkeletonNode, Skin},
+    transform::{NormalMatrixRaw, Position, Rotation, Scale, Transform, TransformRaw},
 };
@@ -1,12 +1,16 @@
-use crate::utils::load_texture;
+use crate::{animation::AnimationClip, utils::load_texture};
 
-use super::{geometry::Material, Mesh};
+use super::{geometry::Material, Mesh, Skin};
 
 type MaterialIndex = usize;
 
 pub struct Model {
     pub meshes: Vec<(Mesh, MaterialIndex)>,
     pub materials: Vec<Material>,
+    /// Skeleton the skinned meshes are bound to, if any.
+    pub skin: Option<Skin>,
+    /// Animations of the skeleton nodes, which `AnimationPlayer`s refer to by index.
+    pub animations: Vec<AnimationClip>,
 }
 
 impl Model {
@@ -17,16 +21,18 @@ impl Model {
     ) -> anyhow::Result<Self> {
         let texture = load_texture(texture_path, device, queue)?;
 
-        let materials = vec![Material {
-            name: String::from("Cube material"),
-            texture,
-        }];
+        let materials = vec![Material::new("Cube material", texture)];
 
         #[rustfmt::skip]
         let meshes = vec![
             (Mesh::create_cube(device, queue)?, 0)
         ];
 
-        Ok(Self { meshes, materials })
+        Ok(Self {
+            meshes,
+            materials,
+            skin: None,
+            animations: Vec::new(),
+        })
     }
 }
@@ -0,0 +1,104 @@
+use cgmath::{Matrix4, One, Quaternion, Vector3};
+
+/// Local transform of a skeleton node, split the way animation channels target it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeTransform {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for NodeTransform {
+    fn default() -> Self {
+        Self {
+            translation: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl NodeTransform {
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SkeletonNode {
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    /// Transform of the node when no animation drives it.
+    pub rest: NodeTransform,
+    /// Morph target weights of the mesh of the node when no animation drives them, empty when it
+    /// has no morph targets.
+    pub weights: Vec<f32>,
+}
+
+/// State of the skeleton nodes that animations drive, indexed like `Skin::nodes`.
+#[derive(Clone, Debug, Default)]
+pub struct Pose {
+    pub transforms: Vec<NodeTransform>,
+    pub weights: Vec<Vec<f32>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Joint {
+    /// Index of the node in the skeleton.
+    pub node: usize,
+    /// Brings vertices from model space into the space of the joint at bind time.
+    pub inverse_bind_matrix: Matrix4<f32>,
+}
+
+/// Node hierarchy of a model and the joints its skinned meshes are bound to. The joint indices of
+/// the vertices index into `joints`. Models with morph targets but no joints have one too, so
+/// animations can drive their weights.
+#[derive(Clone, Debug, Default)]
+pub struct Skin {
+    pub nodes: Vec<SkeletonNode>,
+    pub joints: Vec<Joint>,
+}
+
+impl Skin {
+    pub fn rest_pose(&self) -> Pose {
+        Pose {
+            transforms: self.nodes.iter().map(|node| node.rest).collect(),
+            weights: self.nodes.iter().map(|node| node.weights.clone()).collect(),
+        }
+    }
+
+    /// Model space transform of each joint in `pose` relative to its bind transform, in the order
+    /// of `joints`.
+    pub fn joint_matrices(&self, pose: &[NodeTransform]) -> Vec<Matrix4<f32>> {
+        let mut globals = vec![None; self.nodes.len()];
+
+        self.joints
+            .iter()
+            .map(|joint| {
+                self.global_transform(joint.node, pose, &mut globals) * joint.inverse_bind_matrix
+            })
+            .collect()
+    }
+
+    fn global_transform(
+        &self,
+        node: usize,
+        pose: &[NodeTransform],
+        globals: &mut [Option<Matrix4<f32>>],
+    ) -> Matrix4<f32> {
+        if let Some(global) = globals[node] {
+            return global;
+        }
+
+        let local = pose[node].matrix();
+        let global = match self.nodes[node].parent {
+            Some(parent) => self.global_transform(parent, pose, globals) * local,
+            None => local,
+        };
+
+        globals[node] = Some(global);
+        global
+    }
+}
@@ -1,3 +1,5 @@
+use cgmath::{Matrix, SquareMatrix};
+
 #[derive(Clone, Debug)]
 pub struct Position(pub f32, pub f32, pub f32);
 
@@ -53,7 +55,11 @@ pub struct Transform {
 
 pub type TransformRaw = [[f32; 4]; 4];
 
-impl From<&Transform> for TransformRaw {
+/// Inverse transpose of the upper 3x3 of the model matrix, with columns padded to match
+/// WGSL's `mat3x3` layout.
+pub type NormalMatrixRaw = [[f32; 4]; 3];
+
+impl From<&Transform> for cgmath::Matrix4<f32> {
     fn from(transform: &Transform) -> Self {
         let translation_matrix =
             cgmath::Matrix4::from_translation(cgmath::Vector3::from(&transform.position));
@@ -66,6 +72,32 @@ impl From<&Transform> for TransformRaw {
 
         let rotation_matrix = cgmath::Matrix4::from(cgmath::Quaternion::from(&transform.rotation));
 
-        (translation_matrix * scale_matrix * rotation_matrix).into()
+        translation_matrix * scale_matrix * rotation_matrix
+    }
+}
+
+impl From<&Transform> for TransformRaw {
+    fn from(transform: &Transform) -> Self {
+        cgmath::Matrix4::from(transform).into()
     }
 }
+
+impl From<&Transform> for NormalMatrixRaw {
+    fn from(transform: &Transform) -> Self {
+        normal_matrix(&cgmath::Matrix4::from(transform))
+    }
+}
+
+pub(super) fn normal_matrix(m: &cgmath::Matrix4<f32>) -> NormalMatrixRaw {
+    let m = cgmath::Matrix3::from_cols(m.x.truncate(), m.y.truncate(), m.z.truncate());
+    let n = m
+        .invert()
+        .unwrap_or(cgmath::Matrix3::identity())
+        .transpose();
+
+    [
+        n.x.extend(0.0).into(),
+        n.y.extend(0.0).into(),
+        n.z.extend(0.0).into(),
+    ]
+}
@@ -1,8 +1,21 @@
-use crate::components::{Mesh, Model, Transform};
+use crate::{
+    animation::AnimationPlayer,
+    assets::Handle,
+    components::{Instance, Light, Model, Transform},
+};
 
 pub struct Entity {
-    pub model: Model,
+    /// Entities that share a model and the same pose are drawn together in one instanced draw.
+    pub model: Option<Handle<Model>>,
+    pub light: Option<Light>,
     pub transform: Transform,
+    /// Poses the skeleton of the model. Skinned models without one stay in their rest pose.
+    pub animation: Option<AnimationPlayer>,
+    /// Weights of the morph targets of the meshes of the model, overriding the animated and
+    /// default ones. Each mesh uses as many as it has targets.
+    pub morph_weights: Option<Vec<f32>>,
+    /// Copies of the model drawn in place of the single one at the entity transform.
+    pub instances: Option<Vec<Instance>>,
 }
 
 impl Entity {
@@ -11,21 +24,29 @@ impl Entity {
     }
 }
 
+#[derive(Default)]
 pub struct EntityBuilder {
-    model: Option<Model>,
+    model: Option<Handle<Model>>,
+    light: Option<Light>,
     transform: Transform,
+    animation: Option<AnimationPlayer>,
+    morph_weights: Option<Vec<f32>>,
+    instances: Option<Vec<Instance>>,
 }
 
 impl EntityBuilder {
     pub fn new() -> Self {
-        Self {
-            model: None,
-            transform: Transform::default(),
-        }
+        Self::default()
+    }
+
+    /// Takes the model, or a handle to a shared one.
+    pub fn model(mut self, model: impl Into<Handle<Model>>) -> Self {
+        self.model = Some(model.into());
+        self
     }
 
-    pub fn model(mut self, model: Model) -> Self {
-        self.model = Some(model);
+    pub fn light(mut self, light: Light) -> Self {
+        self.light = Some(light);
         self
     }
 
@@ -34,10 +55,34 @@ impl EntityBuilder {
         self
     }
 
+    pub fn animation(mut self, animation: AnimationPlayer) -> Self {
+        self.animation = Some(animation);
+        self
+    }
+
+    pub fn morph_weights(mut self, weights: Vec<f32>) -> Self {
+        self.morph_weights = Some(weights);
+        self
+    }
+
+    pub fn instances(mut self, instances: Vec<Instance>) -> Self {
+        self.instances = Some(instances);
+        self
+    }
+
     pub fn build(self) -> Entity {
+        assert!(
+            self.model.is_some() || self.light.is_some(),
+            "Missing model or light when creating entity"
+        );
+
         Entity {
-            model: self.model.expect("Missing model when creating entity"),
+            model: self.model,
+            light: self.light,
             transform: self.transform,
+            animation: self.animation,
+            morph_weights: self.morph_weights,
+            instances: self.instances,
         }
     }
 }
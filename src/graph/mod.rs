@@ -0,0 +1,364 @@
+use anyhow::bail;
+
+use crate::graphics::Frame;
+
+mod transients;
+
+pub use self::transients::TextureDesc;
+use self::transients::{TransientKey, TransientPool};
+
+/// Texture or buffer declared in a frame graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ResourceId(usize);
+
+/// How a node uses a texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Access {
+    /// Sampled or loaded by the shaders of the node.
+    Read,
+    /// Rendered to as a color or depth attachment, or stored to by a compute shader.
+    Write,
+}
+
+enum Source<'a> {
+    /// Created by the graph for the frame.
+    Transient(TextureDesc),
+    /// Owned outside the graph, like the frame or the shadow maps, whose contents outlive it.
+    Imported(&'a wgpu::TextureView),
+    /// Buffer owned outside the graph, like the light clusters.
+    Buffer(&'a wgpu::Buffer),
+}
+
+struct Resource<'a> {
+    name: &'static str,
+    source: Source<'a>,
+}
+
+type Record<'a> = Box<dyn FnOnce(&mut wgpu::CommandEncoder, &NodeResources) + 'a>;
+
+struct Node<'a> {
+    name: &'static str,
+    accesses: Vec<(ResourceId, Access)>,
+    record: Option<Record<'a>>,
+}
+
+impl Node<'_> {
+    fn writes(&self, resource: ResourceId) -> bool {
+        self.accesses.contains(&(resource, Access::Write))
+    }
+}
+
+/// Kept between frames so the transient textures of a frame are reused by the next ones.
+#[derive(Default)]
+pub struct RenderGraph {
+    pool: TransientPool,
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts the graph of a frame, whose nodes draw into `frame`.
+    pub fn begin<'a>(&'a mut self, frame: &'a Frame) -> FrameGraph<'a> {
+        let size = frame.texture().size();
+        FrameGraph {
+            pool: &mut self.pool,
+            size: [size.width, size.height],
+            resources: vec![Resource {
+                name: "frame",
+                source: Source::Imported(&frame.view),
+            }],
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Number of textures kept for the transient resources.
+    pub fn transient_textures(&self) -> usize {
+        self.pool.len()
+    }
+}
+
+/// Passes of one frame, as nodes declaring the resources they read and write. The graph orders
+/// the nodes after the ones writing what they read, skips the ones nothing uses, creates the
+/// transient textures with the usages the nodes need, and records every node into one encoder.
+/// wgpu inserts the barriers between the render passes of the encoder from those usages.
+pub struct FrameGraph<'a> {
+    pool: &'a mut TransientPool,
+    size: [u32; 2],
+    resources: Vec<Resource<'a>>,
+    nodes: Vec<Node<'a>>,
+}
+
+impl<'a> FrameGraph<'a> {
+    /// Color texture of the frame.
+    pub fn target(&self) -> ResourceId {
+        ResourceId(0)
+    }
+
+    /// Width and height of the frame.
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Declares a texture that only lives during the frame. Its contents start undefined, so the
+    /// first node writing it should clear it.
+    pub fn create_texture(&mut self, name: &'static str, desc: TextureDesc) -> ResourceId {
+        self.add_resource(name, Source::Transient(desc))
+    }
+
+    /// Declares a texture owned outside the graph. Nodes writing it are always run.
+    pub fn import_texture(
+        &mut self,
+        name: &'static str,
+        view: &'a wgpu::TextureView,
+    ) -> ResourceId {
+        self.add_resource(name, Source::Imported(view))
+    }
+
+    /// Declares a buffer owned outside the graph, which orders the nodes writing it before the
+    /// ones reading it. Nodes writing it are always run.
+    pub fn import_buffer(&mut self, name: &'static str, buffer: &'a wgpu::Buffer) -> ResourceId {
+        self.add_resource(name, Source::Buffer(buffer))
+    }
+
+    fn add_resource(&mut self, name: &'static str, source: Source<'a>) -> ResourceId {
+        self.resources.push(Resource { name, source });
+        ResourceId(self.resources.len() - 1)
+    }
+
+    /// Starts declaring a node. Nodes writing the same texture run in the order they're added.
+    pub fn add_node(&mut self, name: &'static str) -> NodeBuilder<'_, 'a> {
+        NodeBuilder {
+            graph: self,
+            node: Node {
+                name,
+                accesses: Vec::new(),
+                record: None,
+            },
+        }
+    }
+
+    /// Names of the nodes that would run, in order.
+    pub fn schedule(&self) -> anyhow::Result<Vec<&'static str>> {
+        Ok(self
+            .compile()?
+            .into_iter()
+            .map(|node| self.nodes[node].name)
+            .collect())
+    }
+
+    /// Records the nodes into one command encoder and submits it.
+    pub fn execute(mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> anyhow::Result<()> {
+        let order = self.compile()?;
+
+        // Transients get a pooled texture from their first use to their last one, after which
+        // later resources with the same description can take it
+        let mut usages = vec![wgpu::TextureUsages::empty(); self.resources.len()];
+        let mut last_uses = vec![0; self.resources.len()];
+        for (position, &node) in order.iter().enumerate() {
+            for &(ResourceId(resource), access) in &self.nodes[node].accesses {
+                usages[resource] |= match access {
+                    Access::Read => wgpu::TextureUsages::TEXTURE_BINDING,
+                    Access::Write => wgpu::TextureUsages::RENDER_ATTACHMENT,
+                };
+                last_uses[resource] = position;
+            }
+        }
+
+        let mut textures = vec![None; self.resources.len()];
+        for (position, &node) in order.iter().enumerate() {
+            for &(ResourceId(resource), _) in &self.nodes[node].accesses {
+                let Source::Transient(desc) = self.resources[resource].source else {
+                    continue;
+                };
+                if textures[resource].is_none() {
+                    let key = TransientKey {
+                        format: desc.format,
+                        size: desc.size.unwrap_or(self.size),
+                        sample_count: desc.sample_count,
+                        usage: usages[resource],
+                    };
+                    let name = self.resources[resource].name;
+                    textures[resource] = Some(self.pool.acquire(device, key, name));
+                }
+            }
+
+            for &(ResourceId(resource), _) in &self.nodes[node].accesses {
+                if last_uses[resource] == position {
+                    if let Some(texture) = textures[resource] {
+                        self.pool.release(texture);
+                    }
+                }
+            }
+        }
+
+        let pool = &*self.pool;
+        let views = self
+            .resources
+            .iter()
+            .zip(&textures)
+            .map(|(resource, texture)| match (&resource.source, texture) {
+                (Source::Imported(view), _) => Some(*view),
+                (Source::Transient(_), Some(texture)) => Some(pool.view(*texture)),
+                // Only used by culled nodes
+                (Source::Transient(_), None) | (Source::Buffer(_), _) => None,
+            })
+            .collect();
+        let buffers = self
+            .resources
+            .iter()
+            .map(|resource| match resource.source {
+                Source::Buffer(buffer) => Some(buffer),
+                _ => None,
+            })
+            .collect();
+        let resources = NodeResources {
+            device,
+            views,
+            buffers,
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render encoder"),
+        });
+        for node in order {
+            if let Some(record) = self.nodes[node].record.take() {
+                record(&mut encoder, &resources);
+            }
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        self.pool.end_frame();
+        Ok(())
+    }
+
+    /// Orders the nodes that contribute to an imported texture.
+    fn compile(&self) -> anyhow::Result<Vec<usize>> {
+        let writers = |resource: ResourceId| {
+            (0..self.nodes.len()).filter(move |&node| self.nodes[node].writes(resource))
+        };
+
+        // Readers wait for every writer, writers for the writers added before them
+        let mut dependencies = vec![Vec::new(); self.nodes.len()];
+        for (node, declared) in self.nodes.iter().enumerate() {
+            for &(resource, access) in &declared.accesses {
+                let Some(Resource { name, source }) = self.resources.get(resource.0) else {
+                    bail!("`{}` uses a resource of another graph", declared.name);
+                };
+
+                let before = match access {
+                    Access::Read => writers(resource)
+                        .filter(|&writer| writer != node)
+                        .collect::<Vec<_>>(),
+                    Access::Write => writers(resource).filter(|&writer| writer < node).collect(),
+                };
+                if access == Access::Read
+                    && before.is_empty()
+                    && matches!(source, Source::Transient(_))
+                {
+                    bail!("`{}` reads `{}`, which no node writes", declared.name, name);
+                }
+
+                dependencies[node].extend(before);
+            }
+        }
+
+        // Nodes writing imported resources are kept, and so are the ones they depend on
+        let mut used = self
+            .nodes
+            .iter()
+            .map(|node| {
+                node.accesses.iter().any(|&(ResourceId(resource), access)| {
+                    access == Access::Write
+                        && !matches!(self.resources[resource].source, Source::Transient(_))
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut pending = (0..self.nodes.len())
+            .filter(|&node| used[node])
+            .collect::<Vec<_>>();
+        while let Some(node) = pending.pop() {
+            for &dependency in &dependencies[node] {
+                if !used[dependency] {
+                    used[dependency] = true;
+                    pending.push(dependency);
+                }
+            }
+        }
+
+        // Topological order, keeping the order the nodes were added in where they're independent
+        let mut order = Vec::new();
+        let mut scheduled = vec![false; self.nodes.len()];
+        while order.len() < used.iter().filter(|&&used| used).count() {
+            let next = (0..self.nodes.len()).find(|&node| {
+                used[node]
+                    && !scheduled[node]
+                    && dependencies[node]
+                        .iter()
+                        .all(|&dependency| scheduled[dependency])
+            });
+
+            let Some(next) = next else {
+                let cycle = (0..self.nodes.len())
+                    .filter(|&node| used[node] && !scheduled[node])
+                    .map(|node| format!("`{}`", self.nodes[node].name))
+                    .collect::<Vec<_>>();
+                bail!("The nodes {} depend on each other", cycle.join(", "));
+            };
+            scheduled[next] = true;
+            order.push(next);
+        }
+
+        Ok(order)
+    }
+}
+
+pub struct NodeBuilder<'g, 'a> {
+    graph: &'g mut FrameGraph<'a>,
+    node: Node<'a>,
+}
+
+impl<'a> NodeBuilder<'_, 'a> {
+    /// Samples or loads `resource`, after the nodes writing it.
+    pub fn read(mut self, resource: ResourceId) -> Self {
+        self.node.accesses.push((resource, Access::Read));
+        self
+    }
+
+    /// Renders or stores to `resource`.
+    pub fn write(mut self, resource: ResourceId) -> Self {
+        self.node.accesses.push((resource, Access::Write));
+        self
+    }
+
+    /// Adds the node, which records its passes with `record` when the graph executes.
+    pub fn record(mut self, record: impl FnOnce(&mut wgpu::CommandEncoder, &NodeResources) + 'a) {
+        self.node.record = Some(Box::new(record));
+        self.graph.nodes.push(self.node);
+    }
+}
+
+/// Views of the textures of a frame graph and its buffers, for its nodes to render with.
+pub struct NodeResources<'r> {
+    device: &'r wgpu::Device,
+    views: Vec<Option<&'r wgpu::TextureView>>,
+    buffers: Vec<Option<&'r wgpu::Buffer>>,
+}
+
+impl NodeResources<'_> {
+    /// Device to create the bind groups of transient textures with.
+    pub fn device(&self) -> &wgpu::Device {
+        self.device
+    }
+
+    /// View of a texture the node declared.
+    pub fn view(&self, resource: ResourceId) -> &wgpu::TextureView {
+        self.views[resource.0].expect("The node didn't declare the texture")
+    }
+
+    /// Buffer the node declared.
+    pub fn buffer(&self, resource: ResourceId) -> &wgpu::Buffer {
+        self.buffers[resource.0].expect("The node didn't declare the buffer")
+    }
+}
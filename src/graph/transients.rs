@@ -0,0 +1,112 @@
+/// Texture the graph creates for a frame. Textures with the same description and usages are
+/// reused across frames, and by resources of the same frame whose lifetimes don't overlap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureDesc {
+    pub format: wgpu::TextureFormat,
+    /// Width and height, or the size of the frame when `None`.
+    pub size: Option<[u32; 2]>,
+    pub sample_count: u32,
+}
+
+impl TextureDesc {
+    /// Single sampled texture of the size of the frame.
+    pub fn frame_sized(format: wgpu::TextureFormat) -> Self {
+        Self {
+            format,
+            size: None,
+            sample_count: 1,
+        }
+    }
+
+    pub fn with_sample_count(self, sample_count: u32) -> Self {
+        Self {
+            sample_count,
+            ..self
+        }
+    }
+}
+
+/// What a pooled texture was created with.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) struct TransientKey {
+    pub format: wgpu::TextureFormat,
+    pub size: [u32; 2],
+    pub sample_count: u32,
+    pub usage: wgpu::TextureUsages,
+}
+
+struct Pooled {
+    key: TransientKey,
+    view: wgpu::TextureView,
+    /// Whether a resource of the current frame holds it.
+    in_use: bool,
+    used_this_frame: bool,
+}
+
+/// Textures kept between frames for the transient resources of the graph.
+#[derive(Default)]
+pub(super) struct TransientPool {
+    textures: Vec<Pooled>,
+}
+
+impl TransientPool {
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    /// Index of a free texture created with `key`, created when there's none.
+    pub fn acquire(&mut self, device: &wgpu::Device, key: TransientKey, label: &str) -> usize {
+        let free = self
+            .textures
+            .iter()
+            .position(|pooled| !pooled.in_use && pooled.key == key);
+
+        let index = free.unwrap_or_else(|| {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: key.size[0],
+                    height: key.size[1],
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: key.sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format: key.format,
+                usage: key.usage,
+                view_formats: &[],
+            });
+
+            self.textures.push(Pooled {
+                key,
+                view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                in_use: false,
+                used_this_frame: false,
+            });
+            self.textures.len() - 1
+        });
+
+        let pooled = &mut self.textures[index];
+        pooled.in_use = true;
+        pooled.used_this_frame = true;
+        index
+    }
+
+    /// Makes a texture available to the resources used later in the frame.
+    pub fn release(&mut self, index: usize) {
+        self.textures[index].in_use = false;
+    }
+
+    pub fn view(&self, index: usize) -> &wgpu::TextureView {
+        &self.textures[index].view
+    }
+
+    /// Drops the textures no resource used this frame, like the ones of the previous frame size.
+    pub fn end_frame(&mut self) {
+        self.textures.retain(|pooled| pooled.used_this_frame);
+        for pooled in &mut self.textures {
+            pooled.in_use = false;
+            pooled.used_this_frame = false;
+        }
+    }
+}
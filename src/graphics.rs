@@ -1,10 +1,29 @@
-use crate::window::Window;
+use crate::{capture, texture::Texture, window::Window};
 
 pub struct GraphicsContext<'a> {
-    pub surface: wgpu::Surface<'a>,
+    pub target: RenderTarget<'a>,
     pub config: wgpu::SurfaceConfiguration,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
+    /// Adapter the device was created from, to query what its formats support.
+    pub adapter: wgpu::Adapter,
+}
+
+/// Where a frame ends up: the window surface or an offscreen color texture.
+pub enum RenderTarget<'a> {
+    Surface(wgpu::Surface<'a>),
+    Offscreen(Texture),
+}
+
+/// Color view for the current frame. Surface frames must be presented once recorded.
+pub struct Frame<'t> {
+    pub view: wgpu::TextureView,
+    source: FrameSource<'t>,
+}
+
+enum FrameSource<'t> {
+    Surface(wgpu::SurfaceTexture),
+    Offscreen(&'t wgpu::Texture),
 }
 
 impl<'a> GraphicsContext<'a> {
@@ -50,8 +69,12 @@ impl<'a> GraphicsContext<'a> {
 
         let (height, width) = window.inner_size();
 
+        // Reading frames back needs the surface to be a copy source, which not every platform allows
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (surface_capabilities.usages & wgpu::TextureUsages::COPY_SRC);
+
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: surface_format,
             width,
             height,
@@ -64,10 +87,128 @@ impl<'a> GraphicsContext<'a> {
         surface.configure(&device, &config);
 
         GraphicsContext {
-            surface,
+            target: RenderTarget::Surface(surface),
+            config,
+            device,
+            queue,
+            adapter,
+        }
+    }
+
+    /// Creates a context without a window that renders into an offscreen texture of the given
+    /// size. Any adapter is accepted, including software ones, and the backends can be narrowed
+    /// down with the `WGPU_BACKEND` environment variable.
+    pub async fn new_headless(width: u32, height: u32) -> anyhow::Result<GraphicsContext<'a>> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all()),
+            dx12_shader_compiler: Default::default(),
+            flags: Default::default(),
+            gles_minor_version: Default::default(),
+        });
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::None,
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            })
+            .await
+            .ok_or_else(|| {
+                anyhow::anyhow!("No graphics adapter available for headless rendering")
+            })?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits::downlevel_defaults()
+                        .using_resolution(adapter.limits()),
+                },
+                None,
+            )
+            .await?;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+
+        let target = RenderTarget::Offscreen(Texture::create_render_target(
+            &device,
+            &config,
+            "offscreen_target",
+        ));
+
+        Ok(GraphicsContext {
+            target,
             config,
             device,
             queue,
+            adapter,
+        })
+    }
+}
+
+impl<'a> RenderTarget<'a> {
+    pub fn current_frame(&self) -> Frame<'_> {
+        match self {
+            RenderTarget::Surface(surface) => {
+                let surface_texture = surface.get_current_texture().unwrap();
+                let view = surface_texture
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+
+                Frame {
+                    view,
+                    source: FrameSource::Surface(surface_texture),
+                }
+            }
+            RenderTarget::Offscreen(texture) => Frame {
+                view: texture
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default()),
+                source: FrameSource::Offscreen(&texture.texture),
+            },
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        match self {
+            RenderTarget::Surface(surface) => surface.configure(device, config),
+            RenderTarget::Offscreen(texture) => {
+                *texture = Texture::create_render_target(device, config, "offscreen_target");
+            }
+        }
+    }
+}
+
+impl Frame<'_> {
+    pub fn texture(&self) -> &wgpu::Texture {
+        match &self.source {
+            FrameSource::Surface(surface_texture) => &surface_texture.texture,
+            FrameSource::Offscreen(texture) => texture,
+        }
+    }
+
+    /// Reads the frame back into CPU memory. Surface frames can only be captured before `present`.
+    pub fn capture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> anyhow::Result<image::RgbaImage> {
+        capture::read_texture(device, queue, self.texture())
+    }
+
+    pub fn present(self) {
+        if let FrameSource::Surface(surface_texture) = self.source {
+            surface_texture.present();
         }
     }
 }
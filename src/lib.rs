@@ -1,31 +1,56 @@
+mod animation;
+mod assets;
 mod camera;
+mod capture;
 mod components;
 mod entity;
+mod graph;
 mod graphics;
 mod pass;
 mod texture;
 mod utils;
 mod window;
 
-use camera::{Camera, CameraController, CameraDescriptor};
+use camera::CameraController;
 use cgmath::Deg;
-use components::{Model, Position, Scale, Transform};
-use entity::Entity;
-use graphics::GraphicsContext;
-use pass::{Pass, PhongPass};
 
-use texture::Texture;
-use utils::load_model;
-use window::{Event, Window};
+pub use animation::{
+    AnimationClip, AnimationPlayer, Channel, Interpolation, Keyframes, PlayingClip,
+};
+pub use assets::{
+    asset_root_from_args, asset_roots, resolve_asset, set_asset_roots, Assets, Handle,
+    ASSET_ROOTS_ENV, ASSET_ROOT_FLAG,
+};
+pub use camera::{Camera, CameraDescriptor};
+pub use capture::{read_texture, save_png};
+pub use components::{
+    Attenuation, Instance, Joint, Light, LightKind, Material, Mesh, Model, MorphTargets,
+    NodeTransform, Pose, Position, Rotation, Scale, SkeletonNode, Skin, Transform,
+};
+pub use entity::{Entity, EntityBuilder};
+pub use graph::{FrameGraph, NodeBuilder, NodeResources, RenderGraph, ResourceId, TextureDesc};
+pub use graphics::{Frame, GraphicsContext, RenderTarget};
+pub use pass::{
+    preprocess, render_path_from_args, shader_directory_from_args, Pass, PbrPass, RenderPath,
+    ShaderInterface, ShadowSettings, RENDER_PATH_FLAG, SHADER_DIRECTORY_FLAG,
+};
+pub use texture::Texture;
+pub use utils::{load_linear_texture, load_model, load_texture};
+pub use window::{Event, Key, Window};
 
 pub async fn run() {
+    if let Some(root) = asset_root_from_args(std::env::args().skip(1)) {
+        set_asset_roots([root]);
+    }
+
     let window = Window::new();
 
     let GraphicsContext {
         device,
         queue,
         mut config,
-        surface,
+        mut target,
+        ..
     }: GraphicsContext = GraphicsContext::new(&window).await;
 
     let object = Entity::builder()
@@ -37,47 +62,15 @@ pub async fn run() {
         })
         .build();
 
-    // let object2 = Entity::builder()
-    //     .mesh(
-    //         Mesh::create_cube(&device, &queue, "textures/test.png")
-    //             .await
-    //             .expect("Error when creating cube"),
-    //     )
-    //     .transform(Transform {
-    //         position: Position(-1.1, 0.0, -1.9),
-    //         scale: Scale(1.0, 1.0, 1.0),
-    //         ..Default::default()
-    //     })
-    //     .build();
-
-    // let object3 = Entity::builder()
-    //     .mesh(
-    //         Mesh::create_cube(&device, &queue, "textures/test.png")
-    //             .await
-    //             .expect("Error when creating cube"),
-    //     )
-    //     .transform(Transform {
-    //         position: Position(-1.1, 0.0, -4.1),
-    //         scale: Scale(1.0, 1.0, 1.0),
-    //         ..Default::default()
-    //     })
-    //     .build();
-
-    // let object4 = Entity::builder()
-    //     .mesh(
-    //         Mesh::create_cube(&device, &queue, "textures/test.png")
-    //             .await
-    //             .expect("Error when creating cube"),
-    //     )
-    //     .transform(Transform {
-    //         position: Position(1.1, 0.0, -4.1),
-    //         scale: Scale(1.0, 1.0, 1.0),
-    //         ..Default::default()
-    //     })
-    //     .build();
+    let assets = Assets::new();
+    if cfg!(debug_assertions) {
+        if let Err(error) = assets.watch() {
+            log::warn!("Assets won't be reloaded when they change: {:#}", error);
+        }
+    }
 
     let cube = Entity::builder()
-        .model(load_model("cube.obj", &device, &queue).unwrap())
+        .model(assets.load_model("cube.obj", &device, &queue).unwrap())
         .transform(Transform {
             position: Position(1.1, 0.0, -4.1),
             scale: Scale(1.0, 1.0, 1.0),
@@ -85,7 +78,25 @@ pub async fn run() {
         })
         .build();
 
-    let entities = &vec![cube, object];
+    let sun = Entity::builder()
+        .light(Light {
+            intensity: 0.6,
+            ..Light::directional([-0.3, -1.0, -0.5])
+        })
+        .build();
+
+    let lamp = Entity::builder()
+        .light(Light {
+            color: [1.0, 0.8, 0.6],
+            ..Light::point(Attenuation::default())
+        })
+        .transform(Transform {
+            position: Position(2.0, 2.0, 0.0),
+            ..Default::default()
+        })
+        .build();
+
+    let entities = &[cube, object, sun, lamp];
 
     let mut camera = Camera::new(
         (0.0, 2.0, 4.0),
@@ -104,7 +115,33 @@ pub async fn run() {
         rotation_speed: 0.01,
     };
 
-    let mut pass = PhongPass::new(&device, &config);
+    let mut pass = match PbrPass::new(&device, &config) {
+        Ok(pass) => pass,
+        Err(error) => {
+            log::error!("Couldn't create the render pass: {:#}", error);
+            return;
+        }
+    };
+    match render_path_from_args(std::env::args().skip(1)) {
+        Ok(render_path) => pass.set_render_path(render_path.unwrap_or_default()),
+        Err(error) => log::warn!("Rendering forward: {:#}", error),
+    }
+    if cfg!(debug_assertions) {
+        match shader_directory_from_args(std::env::args().skip(1)) {
+            Some(directory) => {
+                if let Err(error) = pass.watch_shaders(&assets, directory) {
+                    log::warn!("Shaders won't be reloaded when they change: {:#}", error);
+                }
+            }
+            None => log::info!(
+                "Using the builtin shaders, pass {} <dir> to reload them when they change",
+                SHADER_DIRECTORY_FLAG
+            ),
+        }
+    }
+
+    let mut graph = RenderGraph::new();
+    let mut screenshot_requested = false;
 
     // Event loop
     window.run(|event, window_commands| match event {
@@ -112,11 +149,25 @@ pub async fn run() {
             camera.resize(width, height);
             config.width = width;
             config.height = height;
-            surface.configure(&device, &config);
-            pass.depth_texture = Texture::create_depth_texture(&device, &config, "depth_texture");
+            target.resize(&device, &config);
         }
         Event::Draw => {
-            pass.draw(&surface, &device, &queue, entities, &camera);
+            assets.reload_changed(&device, &queue);
+
+            let frame = target.current_frame();
+            if let Err(error) = pass.draw(&mut graph, &frame, &device, &queue, entities, &camera) {
+                log::error!("Couldn't draw the frame: {:#}", error);
+            }
+
+            if screenshot_requested {
+                screenshot_requested = false;
+                match take_screenshot(&frame, &device, &queue) {
+                    Ok(path) => log::info!("Screenshot saved to {}", path),
+                    Err(error) => log::error!("Couldn't take screenshot: {}", error),
+                }
+            }
+
+            frame.present();
         }
         Event::KeyboardInput(key) => match key {
             window::Key::Left | window::Key::Letter('a') => {
@@ -137,6 +188,7 @@ pub async fn run() {
             window::Key::ShiftLeft => {
                 camera_controller.move_down(&mut camera);
             }
+            window::Key::Letter('p') => screenshot_requested = true,
             window::Key::Escape => window_commands.exit(),
             _ => {}
         },
@@ -147,3 +199,18 @@ pub async fn run() {
         }
     });
 }
+
+fn take_screenshot(
+    frame: &Frame,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<String> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let path = format!("screenshot-{}.png", timestamp);
+
+    save_png(&frame.capture(device, queue)?, &path)?;
+
+    Ok(path)
+}
@@ -0,0 +1,195 @@
+use std::mem::size_of;
+
+use anyhow::{ensure, Context};
+use cgmath::SquareMatrix;
+
+use super::{
+    reflection::ShaderInterface,
+    shader::{builtin_interface, ShaderFeatures, ShaderLibrary, ShaderPipelines},
+};
+use crate::camera::Camera;
+
+/// Froxels along x, y and depth. The tiles are about square on 16:9 targets.
+pub const CLUSTER_GRID: [u32; 3] = [16, 9, 24];
+
+/// Lights each froxel lists at most, which bounds the light lists however many lights the pass
+/// has room for. A froxel reached by more keeps the first ones.
+pub const MAX_CLUSTER_LIGHTS: usize = 256;
+
+/// Invocations of the culling shader per workgroup, one per froxel.
+const WORKGROUP_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ClustersUniform {
+    view: [[f32; 4]; 4],
+    inv_proj: [[f32; 4]; 4],
+    /// Froxels along x, y and depth, and the `u32`s of the light list of each one.
+    grid: [u32; 4],
+    near: f32,
+    far: f32,
+    _padding: [f32; 2],
+}
+
+/// Lists of the lights reaching each froxel of the view frustum. A compute pass fills them each
+/// frame, so the forward pass shades fragments with the lights of their froxel only.
+pub struct LightClusters {
+    pub(super) uniform_buffer: wgpu::Buffer,
+    /// Count of the lights reaching each froxel, followed by the indices of the first
+    /// `MAX_CLUSTER_LIGHTS` of them. A count past the room of the list tells how many overflowed.
+    pub(super) light_lists: wgpu::Buffer,
+    /// `u32`s of the list of each froxel.
+    list_size: u32,
+
+    bind_group: wgpu::BindGroup,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipelines: ShaderPipelines<wgpu::ComputePipeline>,
+}
+
+impl LightClusters {
+    pub fn new(
+        device: &wgpu::Device,
+        lights_buffer: &wgpu::Buffer,
+        max_lights: usize,
+        shader_library: ShaderLibrary,
+    ) -> anyhow::Result<Self> {
+        let interface =
+            reflect_shader().context("The light culling shader doesn't match the pass")?;
+        let bind_group_layout = interface.bind_group_layout(device, 0, "[Clusters] Culling");
+
+        let list_size = 1 + max_lights.clamp(1, MAX_CLUSTER_LIGHTS) as u32;
+        let [x, y, z] = CLUSTER_GRID;
+        let lists_size = (x * y * z * list_size) as wgpu::BufferAddress
+            * size_of::<u32>() as wgpu::BufferAddress;
+        let limits = device.limits();
+        ensure!(
+            lists_size <= limits.max_storage_buffer_binding_size as wgpu::BufferAddress
+                && lists_size <= limits.max_buffer_size,
+            "The light lists of the froxels take {} bytes, more than the device allows",
+            lists_size
+        );
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[Clusters] Uniforms"),
+            size: size_of::<ClustersUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let light_lists = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[Clusters] Light lists"),
+            size: lists_size,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[Clusters] Culling"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: lights_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: light_lists.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Light culling pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Ok(Self {
+            uniform_buffer,
+            light_lists,
+            list_size,
+            bind_group,
+            pipeline_layout,
+            pipelines: ShaderPipelines::new("cluster_lights.wgsl", shader_library, interface),
+        })
+    }
+
+    pub fn set_library(&mut self, library: ShaderLibrary) {
+        self.pipelines.set_library(library);
+    }
+
+    /// Builds the culling pipeline, unless it's built already.
+    pub fn prepare(&mut self, device: &wgpu::Device) {
+        let layout = &self.pipeline_layout;
+        self.pipelines
+            .prepare(device, ShaderFeatures::default(), |module, _| {
+                create_pipeline(device, layout, module)
+            });
+    }
+
+    /// Rebuilds the pipeline when its watched shader changed.
+    pub fn reload_shader(&mut self, device: &wgpu::Device) {
+        let layout = &self.pipeline_layout;
+        self.pipelines
+            .reload(device, |module, _| create_pipeline(device, layout, module));
+    }
+
+    /// Fits the froxels to the frustum of `camera`. Fails when its projection can't be inverted,
+    /// since the culling shader rebuilds the froxels with the inverse.
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera) -> anyhow::Result<()> {
+        let (near, far) = (camera.znear(), camera.zfar());
+        let projection = camera.get_projection_matrix(near, far);
+        let inv_proj = projection
+            .invert()
+            .context("The projection of the camera is singular")?;
+        let [x, y, z] = CLUSTER_GRID;
+
+        let uniform = ClustersUniform {
+            view: camera.get_view_matrix().into(),
+            inv_proj: inv_proj.into(),
+            grid: [x, y, z, self.list_size],
+            near,
+            far,
+            _padding: [0.0; 2],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        Ok(())
+    }
+
+    /// Lists the lights of every froxel. Expects the lights and the froxels to be updated.
+    pub fn cull(&self, encoder: &mut wgpu::CommandEncoder) {
+        let [x, y, z] = CLUSTER_GRID;
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Light culling pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(self.pipelines.get(ShaderFeatures::default()));
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups((x * y * z).div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
+
+/// Resources of the culling shader, which shares the lights and the froxels with the pass.
+fn reflect_shader() -> anyhow::Result<ShaderInterface> {
+    let interface = builtin_interface("cluster_lights.wgsl")?;
+    interface.check_size::<ClustersUniform>("clusters")?;
+    interface.check_size::<u32>("cluster_lights")?;
+
+    Ok(interface)
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+) -> wgpu::ComputePipeline {
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Light culling pipeline"),
+        layout: Some(layout),
+        module: shader,
+        entry_point: "cs_main",
+    })
+}
@@ -0,0 +1,322 @@
+use anyhow::Context;
+
+use super::{
+    pbr::CLEAR_COLOR,
+    reflection::ShaderInterface,
+    shader::{builtin_interface, ShaderFeatures, ShaderLibrary, ShaderPipelines},
+};
+use crate::{
+    components::{InstanceRaw, Vertex},
+    graph::{FrameGraph, NodeResources, ResourceId, TextureDesc},
+    texture::Texture,
+};
+
+/// GL can't load from depth textures, so the depth is written to a color target as well.
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+const ALBEDO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+const NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const MATERIAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+const AMBIENT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Transient textures the geometry pass fills with the closest surface of each pixel, for the
+/// lighting pass to shade.
+#[derive(Clone, Copy)]
+pub struct GBuffer {
+    /// Cleared to 1.0, which marks the pixels without a surface.
+    pub depth: ResourceId,
+    /// Base color, with the texture and tint, and alpha.
+    pub albedo: ResourceId,
+    /// World space normal.
+    pub normal: ResourceId,
+    /// Metalness and roughness.
+    pub material: ResourceId,
+    /// Ambient and emitted light, which the lights are added to.
+    pub ambient: ResourceId,
+    /// Depth buffer the geometry is tested against.
+    pub depth_buffer: ResourceId,
+}
+
+impl GBuffer {
+    pub fn create(graph: &mut FrameGraph) -> Self {
+        let mut texture =
+            |name, format| graph.create_texture(name, TextureDesc::frame_sized(format));
+
+        Self {
+            depth: texture("G-buffer depth", DEPTH_FORMAT),
+            albedo: texture("G-buffer albedo", ALBEDO_FORMAT),
+            normal: texture("G-buffer normal", NORMAL_FORMAT),
+            material: texture("G-buffer material", MATERIAL_FORMAT),
+            ambient: texture("G-buffer ambient", AMBIENT_FORMAT),
+            depth_buffer: texture("Depth", Texture::DEPTH_FORMAT),
+        }
+    }
+}
+
+/// Pipelines of the deferred path: the geometry pass writes the surfaces into the G-buffer, and
+/// a fullscreen pass lights the pixels with a surface with every light. Lights cost once per
+/// pixel instead of once per fragment drawn.
+pub struct DeferredShading {
+    geometry_layout: wgpu::PipelineLayout,
+    pub(super) geometry: ShaderPipelines,
+
+    gbuffer_bind_group_layout: wgpu::BindGroupLayout,
+    lighting_layout: wgpu::PipelineLayout,
+    lighting: ShaderPipelines,
+    /// Format of the color target.
+    format: wgpu::TextureFormat,
+}
+
+impl DeferredShading {
+    pub fn new(
+        device: &wgpu::Device,
+        global_bind_group_layout: &wgpu::BindGroupLayout,
+        local_bind_group_layout: &wgpu::BindGroupLayout,
+        interface: &ShaderInterface,
+        format: wgpu::TextureFormat,
+        shader_library: ShaderLibrary,
+    ) -> anyhow::Result<Self> {
+        let lighting_interface = reflect_lighting_shader(interface)
+            .context("The deferred lighting shader doesn't match the pass")?;
+        let gbuffer_bind_group_layout =
+            lighting_interface.bind_group_layout(device, 1, "[Deferred] G-buffer");
+
+        let geometry_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Deferred geometry pipeline layout"),
+            bind_group_layouts: &[global_bind_group_layout, local_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let lighting_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Deferred lighting pipeline layout"),
+            bind_group_layouts: &[global_bind_group_layout, &gbuffer_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Ok(Self {
+            geometry_layout,
+            geometry: ShaderPipelines::new(
+                "gbuffer.wgsl",
+                shader_library.clone(),
+                interface.clone(),
+            ),
+            gbuffer_bind_group_layout,
+            lighting_layout,
+            lighting: ShaderPipelines::new(
+                "deferred_lighting.wgsl",
+                shader_library,
+                lighting_interface,
+            ),
+            format,
+        })
+    }
+
+    pub fn set_library(&mut self, library: ShaderLibrary) {
+        self.geometry.set_library(library.clone());
+        self.lighting.set_library(library);
+    }
+
+    /// Builds the geometry pipeline for meshes with `features` and the lighting pipeline, unless
+    /// they're built already.
+    pub fn prepare(&mut self, device: &wgpu::Device, features: ShaderFeatures) {
+        let layout = &self.geometry_layout;
+        self.geometry.prepare(device, features, |module, _| {
+            create_geometry_pipeline(device, layout, module)
+        });
+
+        let (layout, format) = (&self.lighting_layout, self.format);
+        self.lighting
+            .prepare(device, ShaderFeatures::default(), |module, _| {
+                create_lighting_pipeline(device, layout, module, format)
+            });
+    }
+
+    /// Rebuilds the pipelines when their watched shaders changed.
+    pub fn reload_shaders(&mut self, device: &wgpu::Device) {
+        let layout = &self.geometry_layout;
+        self.geometry.reload(device, |module, _| {
+            create_geometry_pipeline(device, layout, module)
+        });
+
+        let (layout, format) = (&self.lighting_layout, self.format);
+        self.lighting.reload(device, |module, _| {
+            create_lighting_pipeline(device, layout, module, format)
+        });
+    }
+
+    /// Starts the geometry pass. The caller binds the globals and draws the meshes with the
+    /// pipelines of `geometry`.
+    pub fn begin_geometry<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        resources: &'a NodeResources,
+        gbuffer: &GBuffer,
+    ) -> wgpu::RenderPass<'a> {
+        // Every target is transient, so each one is cleared before its first write. They all
+        // clear to white, the far depth, since GL on llvmpipe mixed differing clear colors up
+        // between the attachments. The others are only read where the depth holds a surface
+        let attachment = |resource, load| {
+            Some(wgpu::RenderPassColorAttachment {
+                view: resources.view(resource),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        };
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Deferred geometry pass"),
+            color_attachments: &[
+                attachment(gbuffer.depth, wgpu::LoadOp::Clear(wgpu::Color::WHITE)),
+                attachment(gbuffer.albedo, wgpu::LoadOp::Clear(wgpu::Color::WHITE)),
+                attachment(gbuffer.normal, wgpu::LoadOp::Clear(wgpu::Color::WHITE)),
+                attachment(gbuffer.material, wgpu::LoadOp::Clear(wgpu::Color::WHITE)),
+                attachment(gbuffer.ambient, wgpu::LoadOp::Clear(wgpu::Color::WHITE)),
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: resources.view(gbuffer.depth_buffer),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    /// Clears `target` and lights the pixels the G-buffer holds a surface for.
+    pub fn light(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        resources: &NodeResources,
+        gbuffer: &GBuffer,
+        target: ResourceId,
+        global_bind_group: &wgpu::BindGroup,
+    ) {
+        let texture = |binding, resource| wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::TextureView(resources.view(resource)),
+        };
+        let gbuffer_bind_group = resources
+            .device()
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("[Deferred] G-buffer"),
+                layout: &self.gbuffer_bind_group_layout,
+                entries: &[
+                    texture(0, gbuffer.depth),
+                    texture(1, gbuffer.albedo),
+                    texture(2, gbuffer.normal),
+                    texture(3, gbuffer.material),
+                    texture(4, gbuffer.ambient),
+                ],
+            });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Deferred lighting pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: resources.view(target),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(CLEAR_COLOR),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(self.lighting.get(ShaderFeatures::default()));
+        render_pass.set_bind_group(0, global_bind_group, &[]);
+        render_pass.set_bind_group(1, &gbuffer_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// Resources of the lighting shader: the globals of the pass and the G-buffer.
+fn reflect_lighting_shader(interface: &ShaderInterface) -> anyhow::Result<ShaderInterface> {
+    let gbuffer = builtin_interface("deferred_lighting.wgsl")?
+        .group(1)
+        .with_unfilterable("gbuffer_depth")?;
+    interface.group(0).merge(gbuffer)
+}
+
+fn create_geometry_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+) -> wgpu::RenderPipeline {
+    let target = |format| {
+        Some(wgpu::ColorTargetState {
+            format,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        })
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Deferred geometry pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc(), InstanceRaw::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[
+                target(DEPTH_FORMAT),
+                target(ALBEDO_FORMAT),
+                target(NORMAL_FORMAT),
+                target(MATERIAL_FORMAT),
+                target(AMBIENT_FORMAT),
+            ],
+        }),
+        primitive: wgpu::PrimitiveState {
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+fn create_lighting_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Deferred lighting pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
@@ -0,0 +1,85 @@
+use std::{marker::PhantomData, mem::size_of};
+
+/// Uniforms of many objects in one buffer, each in a slot the shaders read through a dynamic
+/// offset. All the slots are written at once every frame.
+pub struct DynamicUniforms<T> {
+    label: &'static str,
+    buffer: wgpu::Buffer,
+    /// Distance between slots, the size of `T` rounded up to the offset alignment of the device.
+    stride: wgpu::BufferAddress,
+    capacity: usize,
+    staging: Vec<u8>,
+    _uniform: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> DynamicUniforms<T> {
+    const INITIAL_CAPACITY: usize = 64;
+
+    pub fn new(device: &wgpu::Device, label: &'static str) -> Self {
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let stride = (size_of::<T>() as wgpu::BufferAddress).div_ceil(alignment) * alignment;
+
+        Self {
+            label,
+            buffer: create_buffer(device, label, stride * Self::INITIAL_CAPACITY as u64),
+            stride,
+            capacity: Self::INITIAL_CAPACITY,
+            staging: Vec::new(),
+            _uniform: PhantomData,
+        }
+    }
+
+    /// Size each slot is bound with.
+    pub fn binding_size() -> wgpu::BufferSize {
+        wgpu::BufferSize::new(size_of::<T>() as wgpu::BufferAddress)
+            .expect("Uniforms can't be empty")
+    }
+
+    /// Binds the first slot, the others are reached with `offset`.
+    pub fn binding(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self.buffer,
+            offset: 0,
+            size: Some(Self::binding_size()),
+        })
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Dynamic offset of the slot at `index`.
+    pub fn offset(&self, index: usize) -> wgpu::DynamicOffset {
+        (index as wgpu::BufferAddress * self.stride) as wgpu::DynamicOffset
+    }
+
+    /// Replaces the contents of the slots with `uniforms`. When they don't fit, the buffer is
+    /// replaced by one at least twice as large, so bind groups using the old one are stale.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, uniforms: &[T]) {
+        if uniforms.len() > self.capacity {
+            self.capacity = uniforms.len().max(self.capacity * 2);
+            self.buffer = create_buffer(device, self.label, self.stride * self.capacity as u64);
+        }
+        if uniforms.is_empty() {
+            return;
+        }
+
+        let stride = self.stride as usize;
+        self.staging.clear();
+        self.staging.resize(stride * uniforms.len(), 0);
+        for (slot, uniform) in self.staging.chunks_exact_mut(stride).zip(uniforms) {
+            slot[..size_of::<T>()].copy_from_slice(bytemuck::bytes_of(uniform));
+        }
+
+        queue.write_buffer(&self.buffer, 0, &self.staging);
+    }
+}
+
+fn create_buffer(device: &wgpu::Device, label: &str, size: wgpu::BufferAddress) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -1,33 +1,276 @@
-use crate::{camera::Camera, entity::Entity};
+use std::{path::PathBuf, str::FromStr};
 
-mod phong;
-mod uniform_pool;
+use anyhow::{bail, Context};
+use cgmath::{Rad, SquareMatrix};
 
-pub use self::phong::PhongPass;
+use crate::{
+    assets::crate_directory,
+    camera::Camera,
+    components::{Attenuation, Light, LightKind, Position},
+    entity::Entity,
+    graph::{FrameGraph, RenderGraph, ResourceId},
+    graphics::Frame,
+};
+
+mod clusters;
+mod deferred;
+mod dynamic_uniforms;
+mod pbr;
+mod point_shadow;
+mod preprocessor;
+mod reflection;
+mod shader;
+mod shadow;
+
+pub use self::{
+    pbr::PbrPass, preprocessor::preprocess, reflection::ShaderInterface, shadow::ShadowSettings,
+};
 
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Globals {
     view_position: [f32; 4],
     view_proj: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
 }
 
 impl Globals {
-    pub fn from(camera: &Camera) -> Self {
+    /// Fails when the view projection of `camera` can't be inverted, since the deferred lighting
+    /// rebuilds positions with the inverse.
+    pub fn from(camera: &Camera) -> anyhow::Result<Self> {
         let camera_position = camera.get_position();
-        Self {
+        let view_proj = camera.get_view_projection_matrix();
+        let inv_view_proj = view_proj
+            .invert()
+            .context("The view projection of the camera is singular")?;
+        Ok(Self {
             view_position: [camera_position.x, camera_position.y, camera_position.z, 0.0],
-            view_proj: camera.get_view_projection_matrix().into(),
+            view_proj: view_proj.into(),
+            inv_view_proj: inv_view_proj.into(),
+        })
+    }
+}
+
+/// GPU layout of a light, shared by every light kind. `kind` selects how the fields are read.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightRaw {
+    position: [f32; 4],
+    direction: [f32; 4],
+    color: [f32; 4],
+    attenuation: [f32; 4],
+    cos_inner_cutoff: f32,
+    cos_outer_cutoff: f32,
+    kind: u32,
+    /// Slot of the light in the point shadow maps, or -1 when it doesn't cast shadows.
+    shadow_index: i32,
+}
+
+impl LightRaw {
+    const DIRECTIONAL: u32 = 0;
+    const POINT: u32 = 1;
+    const SPOT: u32 = 2;
+
+    pub fn new(light: &Light, position: &Position) -> Self {
+        let &Position(x, y, z) = position;
+        let [r, g, b] = light.color.map(|c| c * light.intensity);
+
+        let mut raw = Self {
+            position: [x, y, z, 1.0],
+            direction: [0.0, -1.0, 0.0, 0.0],
+            color: [r, g, b, 1.0],
+            attenuation: [1.0, 0.0, 0.0, 0.0],
+            cos_inner_cutoff: 0.0,
+            cos_outer_cutoff: 0.0,
+            kind: Self::DIRECTIONAL,
+            shadow_index: -1,
+        };
+
+        match light.kind {
+            LightKind::Directional {
+                direction: [dx, dy, dz],
+            } => {
+                raw.direction = [dx, dy, dz, 0.0];
+            }
+            LightKind::Point { attenuation } => {
+                raw.kind = Self::POINT;
+                raw.attenuation = attenuation_raw(&attenuation, r.max(g).max(b));
+            }
+            LightKind::Spot {
+                direction: [dx, dy, dz],
+                inner_cutoff,
+                outer_cutoff,
+                attenuation,
+            } => {
+                raw.kind = Self::SPOT;
+                raw.direction = [dx, dy, dz, 0.0];
+                raw.attenuation = attenuation_raw(&attenuation, r.max(g).max(b));
+                raw.cos_inner_cutoff = Rad::from(inner_cutoff).0.cos();
+                raw.cos_outer_cutoff = Rad::from(outer_cutoff).0.cos();
+            }
+        }
+
+        raw
+    }
+
+    pub fn with_shadow_index(mut self, shadow_index: usize) -> Self {
+        self.shadow_index = shadow_index as i32;
+        self
+    }
+}
+
+/// Attenuation factors, followed by the distance past which a light of `brightness` adds less
+/// than `LIGHT_CUTOFF`, which light clusters are culled with.
+fn attenuation_raw(attenuation: &Attenuation, brightness: f32) -> [f32; 4] {
+    let Attenuation {
+        constant,
+        linear,
+        quadratic,
+    } = *attenuation;
+
+    // Solves constant + linear * d + quadratic * d^2 = brightness / LIGHT_CUTOFF
+    let reach = brightness / LIGHT_CUTOFF - constant;
+    let range = if reach <= 0.0 {
+        0.0
+    } else if quadratic > 0.0 {
+        ((linear * linear + 4.0 * quadratic * reach).sqrt() - linear) / (2.0 * quadratic)
+    } else if linear > 0.0 {
+        reach / linear
+    } else {
+        f32::MAX
+    };
+
+    [constant, linear, quadratic, range]
+}
+
+/// Light reaching a surface below which it's left out of the clusters, a step of 8 bit color.
+const LIGHT_CUTOFF: f32 = 1.0 / 256.0;
+
+/// Header of the lights storage buffer, followed by the `LightRaw` array.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightsHeader {
+    count: u32,
+    _padding: [u32; 3],
+}
+
+impl LightsHeader {
+    pub fn new(count: usize) -> Self {
+        Self {
+            count: count as u32,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Command line flag picking the render path, followed by `forward`, `deferred` or `clustered`.
+pub const RENDER_PATH_FLAG: &str = "--render-path";
+
+/// How `PbrPass` lights the scene.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderPath {
+    /// Every light is added up for each fragment as the meshes are drawn.
+    #[default]
+    Forward,
+    /// The meshes are drawn into a G-buffer, which a fullscreen pass lights once per pixel. Scales
+    /// to many more lights, but draws every surface opaque and without MSAA.
+    Deferred,
+    /// A compute pass lists the lights reaching each froxel of the view frustum, and fragments
+    /// add up the lights of their froxel as the meshes are drawn. Scales to many lights like
+    /// deferred, with MSAA and transparent surfaces like forward.
+    Clustered,
+}
+
+impl FromStr for RenderPath {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> anyhow::Result<Self> {
+        match name {
+            "forward" => Ok(Self::Forward),
+            "deferred" => Ok(Self::Deferred),
+            "clustered" => Ok(Self::Clustered),
+            _ => bail!(
+                "Unknown render path `{}`, expected forward, deferred or clustered",
+                name
+            ),
+        }
+    }
+}
+
+/// Render path given with `RENDER_PATH_FLAG` in `args`, if any.
+pub fn render_path_from_args(
+    args: impl IntoIterator<Item = String>,
+) -> anyhow::Result<Option<RenderPath>> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == RENDER_PATH_FLAG {
+            let name = args
+                .next()
+                .with_context(|| format!("{} needs a render path", RENDER_PATH_FLAG))?;
+            return name.parse().map(Some);
+        }
+        if let Some(name) = arg
+            .strip_prefix(RENDER_PATH_FLAG)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return name.parse().map(Some);
         }
     }
+
+    Ok(None)
 }
+
+/// Command line flag giving the directory debug builds read the WGSL shaders from, as
+/// `--shaders <dir>` or `--shaders=<dir>`.
+pub const SHADER_DIRECTORY_FLAG: &str = "--shaders";
+
+/// Directory given with `SHADER_DIRECTORY_FLAG` in `args`. Without the flag, `src/pass/shaders`
+/// in the crate directory when run through Cargo, and `None` otherwise.
+pub fn shader_directory_from_args(args: impl IntoIterator<Item = String>) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == SHADER_DIRECTORY_FLAG {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(directory) = arg
+            .strip_prefix(SHADER_DIRECTORY_FLAG)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(PathBuf::from(directory));
+        }
+    }
+
+    crate_directory().map(|directory| directory.join("src/pass/shaders"))
+}
+
 pub trait Pass {
-    fn draw(
+    /// Updates the buffers and bind groups the pass draws `entities` with.
+    fn prepare(
         &mut self,
-        surface: &wgpu::Surface,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        entities: &Vec<Entity>,
+        entities: &[Entity],
         camera: &Camera,
     );
+
+    /// Adds the nodes drawing what was prepared into `target` to the graph.
+    fn record<'a>(&'a self, graph: &mut FrameGraph<'a>, target: ResourceId);
+
+    /// Prepares the pass and draws it into `frame` through `graph`, as the only pass of the frame.
+    fn draw(
+        &mut self,
+        graph: &mut RenderGraph,
+        frame: &Frame,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        entities: &[Entity],
+        camera: &Camera,
+    ) -> anyhow::Result<()> {
+        self.prepare(device, queue, entities, camera);
+
+        let mut frame_graph = graph.begin(frame);
+        let target = frame_graph.target();
+        self.record(&mut frame_graph, target);
+        frame_graph.execute(device, queue)
+    }
 }
@@ -0,0 +1,1303 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem::{size_of, size_of_val},
+    ops::Range,
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::{ensure, Context};
+use cgmath::{EuclideanSpace, Matrix4, MetricSpace, SquareMatrix};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    assets::{Assets, Handle},
+    camera::Camera,
+    components::{InstanceRaw, LightKind, Material, Model, Vertex},
+    entity::Entity,
+    graph::{FrameGraph, ResourceId, TextureDesc},
+    texture::Texture,
+    utils,
+};
+
+use super::{
+    clusters::{ClustersUniform, LightClusters},
+    deferred::{DeferredShading, GBuffer},
+    dynamic_uniforms::DynamicUniforms,
+    point_shadow::{PointShadowMaps, PointShadowsUniform, CUBE_FACES},
+    reflection::ShaderInterface,
+    shader::{builtin_interface, ShaderFeatures, ShaderLibrary, ShaderPipelines},
+    shadow::{ShadowMap, ShadowSettings, ShadowUniform},
+    Globals, LightRaw, LightsHeader, RenderPath,
+};
+
+/// Background of the frame.
+pub(super) const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.2,
+    g: 0.8,
+    b: 0.5,
+    a: 1.0,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Locals {
+    /// Base color in `xyz` and opacity in `w`.
+    pub base_color: [f32; 4],
+    pub emissive: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+}
+
+impl Locals {
+    fn new(material: &Material) -> Self {
+        let [r, g, b] = material.base_color;
+        let [er, eg, eb] = material.emissive;
+
+        Self {
+            base_color: [r, g, b, material.opacity],
+            emissive: [er, eg, eb, 0.0],
+            metallic: material.metallic,
+            roughness: material.roughness,
+            normal_scale: material.normal_scale,
+            occlusion_strength: material.occlusion_strength,
+        }
+    }
+}
+
+/// Entities drawn together, with one instanced draw per mesh.
+struct Batch<'a> {
+    /// Snapshot of the model, which stays the same for the whole frame even if it's reloaded.
+    model: Arc<Model>,
+    /// Entity whose pose and morph target weights the batch is drawn with.
+    entity: &'a Entity,
+    instances: Range<u32>,
+}
+
+/// Groups the entities that share a model into batches, and adds the instances of each batch
+/// to `instances`. Entities with their own pose or morph target weights get a batch of their own,
+/// and so do the ones with transparent materials, which are sorted by distance.
+fn batch_entities<'a>(entities: &'a [Entity], instances: &mut Vec<InstanceRaw>) -> Vec<Batch<'a>> {
+    let mut groups: Vec<(Arc<Model>, Vec<&Entity>)> = Vec::new();
+    let mut shared = HashMap::new();
+
+    for entity in entities {
+        let Some(model) = &entity.model else {
+            continue;
+        };
+
+        let posed = entity.animation.is_some() || entity.morph_weights.is_some();
+        let transparent = model.get().materials.iter().any(Material::is_transparent);
+        let alone = posed || transparent;
+        match shared.get(&model.as_ptr()) {
+            Some(&group) if !alone => {
+                let (_, members): &mut (_, Vec<_>) = &mut groups[group];
+                members.push(entity);
+            }
+            _ => {
+                if !alone {
+                    shared.insert(model.as_ptr(), groups.len());
+                }
+                groups.push((model.get(), vec![entity]));
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(model, members)| {
+            let start = instances.len() as u32;
+
+            for entity in &members {
+                let m_matrix = Matrix4::from(&entity.transform);
+                match &entity.instances {
+                    Some(entity_instances) => {
+                        instances.extend(entity_instances.iter().map(|instance| {
+                            InstanceRaw::new(
+                                m_matrix * Matrix4::from(&instance.transform),
+                                instance.tint,
+                            )
+                        }));
+                    }
+                    None => instances.push(InstanceRaw::new(m_matrix, [1.0, 1.0, 1.0])),
+                }
+            }
+
+            Batch {
+                model,
+                entity: members[0],
+                instances: start..instances.len() as u32,
+            }
+        })
+        .collect()
+}
+
+/// Starts the morph target weights of a mesh, which follow it as `f32`s.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct MorphWeightsHeader {
+    target_count: u32,
+    vertex_count: u32,
+}
+
+/// Mesh of a batch drawn this frame.
+struct MeshDraw {
+    model: Arc<Model>,
+    mesh: usize,
+    instances: Range<u32>,
+    /// Bind group, uniforms offset and shader features it's drawn with.
+    resources: LocalResources,
+    offset: wgpu::DynamicOffset,
+    features: ShaderFeatures,
+}
+
+/// Resources a local bind group binds. Meshes that bind the same ones share the bind group.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct LocalResources {
+    uniforms: wgpu::Id<wgpu::Buffer>,
+    texture: wgpu::Id<wgpu::TextureView>,
+    sampler: wgpu::Id<wgpu::Sampler>,
+    metallic_roughness: wgpu::Id<wgpu::TextureView>,
+    occlusion: wgpu::Id<wgpu::TextureView>,
+    emissive: wgpu::Id<wgpu::TextureView>,
+    normal: wgpu::Id<wgpu::TextureView>,
+    joints: wgpu::Id<wgpu::Buffer>,
+    morph_deltas: wgpu::Id<wgpu::Buffer>,
+    morph_weights: wgpu::Id<wgpu::Buffer>,
+}
+
+/// Textures bound for the ones a material doesn't have.
+#[derive(Clone)]
+struct PlaceholderTextures {
+    white: Handle<Texture>,
+    flat_normal: Handle<Texture>,
+}
+
+const MORPH_DELTA_SIZE: wgpu::BufferAddress = size_of::<[f32; 4]>() as wgpu::BufferAddress;
+const MORPH_WEIGHTS_HEADER_SIZE: wgpu::BufferAddress =
+    size_of::<MorphWeightsHeader>() as wgpu::BufferAddress;
+
+/// Draws entities with metallic-roughness materials shaded by a Cook-Torrance BRDF, along the
+/// forward, deferred or clustered `RenderPath`.
+pub struct PbrPass {
+    global_bind_group_layout: wgpu::BindGroupLayout,
+    global_uniform_buffer: wgpu::Buffer,
+    lights_buffer: wgpu::Buffer,
+    shadow_uniform_buffer: wgpu::Buffer,
+    point_shadows_buffer: wgpu::Buffer,
+    global_bind_group: wgpu::BindGroup,
+
+    max_lights: usize,
+    shadow_map: ShadowMap,
+    point_shadow_maps: PointShadowMaps,
+
+    local_bind_group_layout: wgpu::BindGroupLayout,
+    /// Resources the shaders bind, which the layouts are made from.
+    interface: ShaderInterface,
+    local_uniforms: DynamicUniforms<Locals>,
+    local_bind_groups: HashMap<LocalResources, wgpu::BindGroup>,
+    /// Transforms and tints of the instances of every batch, one batch after the other.
+    instance_buffer: wgpu::Buffer,
+
+    /// Joint matrices of each skinned batch, by batch index.
+    joint_buffers: Vec<Option<wgpu::Buffer>>,
+    /// Bound in place of the joint matrices for meshes that aren't skinned.
+    identity_joints: wgpu::Buffer,
+    /// Morph target weights of each mesh of each batch, for the meshes with morph targets.
+    morph_weight_buffers: Vec<Option<wgpu::Buffer>>,
+    /// Zeroed, bound in place of the deltas and weights of meshes without morph targets.
+    no_morph_targets: wgpu::Buffer,
+    /// Bound in place of the textures a material doesn't have, created with the first frame.
+    placeholder_textures: Option<PlaceholderTextures>,
+
+    /// Meshes of the batches prepared for the frame, the opaque ones first and the transparent
+    /// ones from the farthest to the closest.
+    draws: Vec<MeshDraw>,
+    /// Shadow cascades and point shadow slots drawn this frame.
+    cascade_count: usize,
+    point_shadow_count: usize,
+
+    pipeline_layout: wgpu::PipelineLayout,
+    shader_library: ShaderLibrary,
+    /// Format of the color target.
+    format: wgpu::TextureFormat,
+    /// Samples per pixel of the forward paths.
+    sample_count: u32,
+    pipelines: ShaderPipelines,
+
+    render_path: RenderPath,
+    deferred: DeferredShading,
+    clusters: LightClusters,
+}
+
+impl PbrPass {
+    pub const DEFAULT_MAX_LIGHTS: usize = 16;
+
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+    ) -> anyhow::Result<PbrPass> {
+        Self::with_max_lights(device, config, Self::DEFAULT_MAX_LIGHTS)
+    }
+
+    /// Creates the pass with room for `max_lights` lights. Lights past that count are ignored.
+    /// Fails when the lights don't fit in a storage buffer of `device`, or when the builtin
+    /// shaders don't bind what the pass expects.
+    pub fn with_max_lights(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        max_lights: usize,
+    ) -> anyhow::Result<PbrPass> {
+        // GLOBAL UNIFORMS
+        let global_size = size_of::<Globals>() as wgpu::BufferAddress;
+        let shadow_size = size_of::<ShadowUniform>() as wgpu::BufferAddress;
+        let point_shadows_size = size_of::<PointShadowsUniform>() as wgpu::BufferAddress;
+        let lights_header_size = size_of::<LightsHeader>() as wgpu::BufferAddress;
+        let lights_size =
+            lights_header_size + (size_of::<LightRaw>() * max_lights.max(1)) as wgpu::BufferAddress;
+        let limits = device.limits();
+        ensure!(
+            lights_size <= limits.max_storage_buffer_binding_size as wgpu::BufferAddress
+                && lights_size <= limits.max_buffer_size,
+            "{} lights take {} bytes, more than a storage buffer of the device can hold",
+            max_lights,
+            lights_size
+        );
+
+        let interface = reflect_shaders().context("The shaders don't match the pass")?;
+        let global_bind_group_layout =
+            interface.bind_group_layout(device, 0, "PBR Globals bind group layout");
+
+        let global_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("PBR Globals buffer"),
+            size: global_size,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let lights_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("PBR Lights buffer"),
+            size: lights_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let shadow_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("PBR Shadow buffer"),
+            size: shadow_size,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let point_shadows_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("PBR Point shadows buffer"),
+            size: point_shadows_size,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // LOCAL UNIFORMS
+        let local_bind_group_layout = interface.bind_group_layout(device, 1, "[PBR] Locals");
+
+        let local_uniforms = DynamicUniforms::new(device, "[PBR] Locals uniforms");
+
+        let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+        let identity_joints = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("[PBR] Identity joints"),
+            contents: bytemuck::cast_slice(&[identity]),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let no_morph_targets = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[PBR] No morph targets"),
+            size: MORPH_WEIGHTS_HEADER_SIZE.max(MORPH_DELTA_SIZE) * 2,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        // Every pipeline of the pass is built from the same library, which `watch_shaders` swaps
+        let shader_library = ShaderLibrary::default();
+        let clusters =
+            LightClusters::new(device, &lights_buffer, max_lights, shader_library.clone())?;
+
+        // SHADOWS
+        let shadow_settings = ShadowSettings::default();
+        let shadow_map = ShadowMap::new(
+            device,
+            &local_bind_group_layout,
+            &interface,
+            shadow_settings,
+            shader_library.clone(),
+        )?;
+        let point_shadow_maps = PointShadowMaps::new(
+            device,
+            &local_bind_group_layout,
+            &interface,
+            &shadow_settings,
+            shader_library.clone(),
+        )?;
+
+        let global_bind_group = create_global_bind_group(
+            device,
+            &global_bind_group_layout,
+            &global_uniform_buffer,
+            &lights_buffer,
+            &shadow_uniform_buffer,
+            &point_shadows_buffer,
+            &shadow_map,
+            &point_shadow_maps,
+            &clusters,
+        );
+
+        // PIPELINE
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render pipeline layout"),
+            bind_group_layouts: &[&global_bind_group_layout, &local_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipelines =
+            ShaderPipelines::new("shader.wgsl", shader_library.clone(), interface.clone());
+        let deferred = DeferredShading::new(
+            device,
+            &global_bind_group_layout,
+            &local_bind_group_layout,
+            &interface,
+            config.format,
+            shader_library.clone(),
+        )?;
+
+        Ok(PbrPass {
+            global_bind_group_layout,
+            global_uniform_buffer,
+            lights_buffer,
+            shadow_uniform_buffer,
+            point_shadows_buffer,
+            global_bind_group,
+
+            max_lights,
+            shadow_map,
+            point_shadow_maps,
+
+            local_bind_group_layout,
+            interface,
+            local_uniforms,
+            local_bind_groups: HashMap::new(),
+            instance_buffer: create_instance_buffer(device, 64),
+
+            joint_buffers: Vec::new(),
+            identity_joints,
+            morph_weight_buffers: Vec::new(),
+            no_morph_targets,
+            placeholder_textures: None,
+
+            draws: Vec::new(),
+            cascade_count: 0,
+            point_shadow_count: 0,
+
+            pipeline_layout,
+            shader_library,
+            format: config.format,
+            sample_count: 1,
+            pipelines,
+
+            render_path: RenderPath::default(),
+            deferred,
+            clusters,
+        })
+    }
+
+    /// Draws the instances of every mesh of each batch with their locals in group 1, and the
+    /// permutation of `pipelines` for their features. Expects the pass to be prepared.
+    fn draw_batches<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        pipelines: &'a ShaderPipelines,
+    ) {
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+        let mut bound = None;
+        for draw in &self.draws {
+            if bound != Some(draw.features) {
+                render_pass.set_pipeline(pipelines.get(draw.features));
+                bound = Some(draw.features);
+            }
+
+            let (mesh, _) = &draw.model.meshes[draw.mesh];
+            render_pass.set_bind_group(1, &self.local_bind_groups[&draw.resources], &[draw.offset]);
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.index_count as u32, 0, draw.instances.clone());
+        }
+    }
+
+    /// Draws the batches into `target`, after the nodes writing `inputs`, like the shadow maps.
+    /// With several samples per pixel, they're drawn into a multisampled texture resolved into
+    /// `target`.
+    fn record_forward<'a>(
+        &'a self,
+        graph: &mut FrameGraph<'a>,
+        target: ResourceId,
+        inputs: &[ResourceId],
+    ) {
+        let samples = |desc: TextureDesc| desc.with_sample_count(self.sample_count);
+        let depth = graph.create_texture(
+            "Depth",
+            samples(TextureDesc::frame_sized(Texture::DEPTH_FORMAT)),
+        );
+        let multisampled = (self.sample_count > 1).then(|| {
+            graph.create_texture(
+                "Multisampled color",
+                samples(TextureDesc::frame_sized(self.format)),
+            )
+        });
+
+        let mut node = graph.add_node("PBR");
+        for &input in inputs {
+            node = node.read(input);
+        }
+        if let Some(multisampled) = multisampled {
+            node = node.write(multisampled);
+        }
+
+        node.write(target)
+            .write(depth)
+            .record(move |encoder, resources| {
+                // The samples only matter until they're resolved
+                let (view, resolve_target, store) = match multisampled {
+                    Some(multisampled) => (
+                        resources.view(multisampled),
+                        Some(resources.view(target)),
+                        wgpu::StoreOp::Discard,
+                    ),
+                    None => (resources.view(target), None, wgpu::StoreOp::Store),
+                };
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Render pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(CLEAR_COLOR),
+                            store,
+                        },
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: resources.view(depth),
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+                render_pass.set_bind_group(0, &self.global_bind_group, &[]);
+                self.draw_batches(&mut render_pass, &self.pipelines);
+            });
+    }
+
+    /// Lists the lights of each froxel, for the clustered forward pass to read.
+    fn record_culling<'a>(&'a self, graph: &mut FrameGraph<'a>) -> ResourceId {
+        let light_lists = graph.import_buffer("Light clusters", &self.clusters.light_lists);
+
+        graph
+            .add_node("Light culling")
+            .write(light_lists)
+            .record(move |encoder, _| self.clusters.cull(encoder));
+
+        light_lists
+    }
+
+    /// Draws the batches into a G-buffer, then lights it with every light in one fullscreen pass.
+    fn record_deferred<'a>(
+        &'a self,
+        graph: &mut FrameGraph<'a>,
+        target: ResourceId,
+        shadow_maps: [ResourceId; 2],
+    ) {
+        let gbuffer = GBuffer::create(graph);
+
+        graph
+            .add_node("Deferred geometry")
+            .write(gbuffer.depth)
+            .write(gbuffer.albedo)
+            .write(gbuffer.normal)
+            .write(gbuffer.material)
+            .write(gbuffer.ambient)
+            .write(gbuffer.depth_buffer)
+            .record(move |encoder, resources| {
+                let mut render_pass = self.deferred.begin_geometry(encoder, resources, &gbuffer);
+                render_pass.set_bind_group(0, &self.global_bind_group, &[]);
+                self.draw_batches(&mut render_pass, &self.deferred.geometry);
+            });
+
+        graph
+            .add_node("Deferred lighting")
+            .read(gbuffer.depth)
+            .read(gbuffer.albedo)
+            .read(gbuffer.normal)
+            .read(gbuffer.material)
+            .read(gbuffer.ambient)
+            .read(shadow_maps[0])
+            .read(shadow_maps[1])
+            .write(target)
+            .record(move |encoder, resources| {
+                self.deferred.light(
+                    encoder,
+                    resources,
+                    &gbuffer,
+                    target,
+                    &self.global_bind_group,
+                );
+            });
+    }
+
+    /// Dev mode: reads the WGSL modules from `directory` instead of the builtin ones, and
+    /// rebuilds the pipelines when `assets` reloads them. A shader with errors logs a diagnostic
+    /// and leaves the previous pipelines in place.
+    pub fn watch_shaders(
+        &mut self,
+        assets: &Assets,
+        directory: impl AsRef<Path>,
+    ) -> anyhow::Result<()> {
+        let directory = directory.as_ref();
+        self.shader_library = ShaderLibrary::watch(assets, directory)?;
+        assets.watch_directory(directory)?;
+
+        self.pipelines.set_library(self.shader_library.clone());
+        self.shadow_map
+            .pipelines
+            .set_library(self.shader_library.clone());
+        self.point_shadow_maps
+            .pipelines
+            .set_library(self.shader_library.clone());
+        self.deferred.set_library(self.shader_library.clone());
+        self.clusters.set_library(self.shader_library.clone());
+        Ok(())
+    }
+
+    fn reload_shaders(&mut self, device: &wgpu::Device) {
+        let (layout, format, sample_count) =
+            (&self.pipeline_layout, self.format, self.sample_count);
+        self.pipelines.reload(device, |module, features| {
+            create_pipeline(device, layout, module, format, features, sample_count)
+        });
+        self.shadow_map.reload_shader(device);
+        self.point_shadow_maps.reload_shader(device);
+        self.deferred.reload_shaders(device);
+        self.clusters.reload_shader(device);
+    }
+
+    /// Builds the pipelines of every pass for meshes with `features`, unless they're built.
+    fn prepare_pipelines(&mut self, device: &wgpu::Device, features: ShaderFeatures) {
+        match self.render_path {
+            RenderPath::Forward | RenderPath::Clustered => {
+                let (layout, format, sample_count) =
+                    (&self.pipeline_layout, self.format, self.sample_count);
+                self.pipelines
+                    .prepare(device, features, |module, features| {
+                        create_pipeline(device, layout, module, format, features, sample_count)
+                    });
+            }
+            RenderPath::Deferred => self.deferred.prepare(device, features),
+        }
+        self.shadow_map.prepare(device, features);
+        self.point_shadow_maps.prepare(device, features);
+    }
+
+    pub fn render_path(&self) -> RenderPath {
+        self.render_path
+    }
+
+    /// Switches between forward, deferred and clustered shading from the next frame on.
+    pub fn set_render_path(&mut self, render_path: RenderPath) {
+        self.render_path = render_path;
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Samples per pixel of the forward and clustered paths, 1 to disable MSAA. Fails when the
+    /// color or depth format doesn't support `sample_count` on `device`, created from `adapter`.
+    /// The deferred path always draws one sample per pixel.
+    pub fn set_sample_count(
+        &mut self,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        sample_count: u32,
+    ) -> anyhow::Result<()> {
+        for format in [self.format, Texture::DEPTH_FORMAT] {
+            // Without the adapter specific features, the device only allows the guaranteed ones
+            let features = if device
+                .features()
+                .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+            {
+                adapter.get_texture_format_features(format)
+            } else {
+                format.guaranteed_format_features(device.features())
+            };
+            ensure!(
+                features.flags.sample_count_supported(sample_count),
+                "{:?} doesn't support {} samples, only {:?}",
+                format,
+                sample_count,
+                features.flags.supported_sample_counts()
+            );
+        }
+
+        if sample_count != self.sample_count {
+            self.sample_count = sample_count;
+            self.pipelines.clear();
+        }
+        Ok(())
+    }
+
+    pub fn shadow_settings(&self) -> ShadowSettings {
+        self.shadow_map.settings
+    }
+
+    /// Rebuilds the shadow maps with `settings`. Fails, keeping the current ones, when the maps
+    /// don't fit in the textures of `device` or the shadow distance isn't positive.
+    pub fn set_shadow_settings(
+        &mut self,
+        device: &wgpu::Device,
+        settings: ShadowSettings,
+    ) -> anyhow::Result<()> {
+        settings.check(&device.limits())?;
+
+        // Both are built before either is replaced, so an error leaves the pass as it was
+        let shadow_map = ShadowMap::new(
+            device,
+            &self.local_bind_group_layout,
+            &self.interface,
+            settings,
+            self.shader_library.clone(),
+        )?;
+        let point_shadow_maps = PointShadowMaps::new(
+            device,
+            &self.local_bind_group_layout,
+            &self.interface,
+            &settings,
+            self.shader_library.clone(),
+        )?;
+        self.shadow_map = shadow_map;
+        self.point_shadow_maps = point_shadow_maps;
+        self.global_bind_group = create_global_bind_group(
+            device,
+            &self.global_bind_group_layout,
+            &self.global_uniform_buffer,
+            &self.lights_buffer,
+            &self.shadow_uniform_buffer,
+            &self.point_shadows_buffer,
+            &self.shadow_map,
+            &self.point_shadow_maps,
+            &self.clusters,
+        );
+        Ok(())
+    }
+}
+
+/// Resources the pass shaders bind, checked against the uniforms written to them. The shadow
+/// and deferred passes bind the locals or globals too, so their groups hold what any of the
+/// shaders use.
+fn reflect_shaders() -> anyhow::Result<ShaderInterface> {
+    let shadow_locals = builtin_interface("shadow.wgsl")?.group(1);
+    let point_shadow_locals = builtin_interface("point_shadow.wgsl")?.group(1);
+    let gbuffer = builtin_interface("gbuffer.wgsl")?;
+    let deferred_globals = builtin_interface("deferred_lighting.wgsl")?.group(0);
+    let interface = builtin_interface("shader.wgsl")?
+        .merge(shadow_locals)?
+        .merge(point_shadow_locals)?
+        .merge(gbuffer)?
+        .merge(deferred_globals)?
+        .with_dynamic_offset("locals")?;
+
+    interface.check_size::<Globals>("globals")?;
+    interface.check_size_of(
+        "lights",
+        "LightsHeader + LightRaw",
+        size_of::<LightsHeader>() + size_of::<LightRaw>(),
+    )?;
+    interface.check_size::<ShadowUniform>("shadow")?;
+    interface.check_size::<PointShadowsUniform>("point_shadows")?;
+    interface.check_size::<ClustersUniform>("clusters")?;
+    interface.check_size::<u32>("cluster_lights")?;
+    interface.check_size::<Locals>("locals")?;
+    interface.check_size::<[[f32; 4]; 4]>("joints")?;
+    interface.check_size::<[f32; 4]>("morph_deltas")?;
+    interface.check_size_of(
+        "morph_weights",
+        "MorphWeightsHeader + f32",
+        MORPH_WEIGHTS_HEADER_SIZE as usize + size_of::<f32>(),
+    )?;
+
+    Ok(interface)
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("[PBR] Instances"),
+        size: size_of::<InstanceRaw>() as wgpu::BufferAddress * capacity,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Reuses the storage buffer in `slot` while it has the right size, or replaces it.
+fn storage_buffer<'a>(
+    slot: &'a mut Option<wgpu::Buffer>,
+    device: &wgpu::Device,
+    label: &str,
+    size: wgpu::BufferAddress,
+) -> &'a wgpu::Buffer {
+    if slot.as_ref().is_some_and(|buffer| buffer.size() != size) {
+        *slot = None;
+    }
+
+    slot.get_or_insert_with(|| {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_global_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    global_uniform_buffer: &wgpu::Buffer,
+    lights_buffer: &wgpu::Buffer,
+    shadow_uniform_buffer: &wgpu::Buffer,
+    point_shadows_buffer: &wgpu::Buffer,
+    shadow_map: &ShadowMap,
+    point_shadow_maps: &PointShadowMaps,
+    clusters: &LightClusters,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("PBR Globals bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: global_uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: lights_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: shadow_uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&shadow_map.texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Sampler(&shadow_map.texture.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: point_shadows_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::TextureView(&point_shadow_maps.texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: clusters.uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 8,
+                resource: clusters.light_lists.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+/// Transparent surfaces are blended over what's behind them, and hide nothing drawn after them.
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    features: ShaderFeatures,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let blend = if features.transparent {
+        wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::OVER,
+        }
+    } else {
+        wgpu::BlendState::REPLACE
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc(), InstanceRaw::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(blend),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: !features.transparent,
+            depth_compare: wgpu::CompareFunction::Less, // 1.
+            stencil: wgpu::StencilState::default(),     // 2.
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+impl super::Pass for PbrPass {
+    fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        entities: &[Entity],
+        camera: &Camera,
+    ) {
+        self.reload_shaders(device);
+
+        match Globals::from(camera) {
+            Ok(globals) => {
+                queue.write_buffer(
+                    &self.global_uniform_buffer,
+                    0,
+                    bytemuck::cast_slice(&[globals]),
+                );
+            }
+            Err(error) => log::error!("Keeping the previous camera: {:#}", error),
+        }
+        if self.render_path == RenderPath::Clustered {
+            if let Err(error) = self.clusters.update(queue, camera) {
+                log::error!("Keeping the previous froxels: {:#}", error);
+            }
+            self.clusters.prepare(device);
+        }
+
+        let lights = entities
+            .iter()
+            .filter_map(|entity| Some((entity.light.as_ref()?, &entity.transform.position)))
+            .take(self.max_lights)
+            .collect::<Vec<_>>();
+
+        // The first directional light casting shadows gets the cascades
+        let shadow_caster =
+            lights
+                .iter()
+                .enumerate()
+                .find_map(|(index, (light, _))| match light.kind {
+                    LightKind::Directional { direction } if light.cast_shadows => {
+                        Some((index, direction))
+                    }
+                    _ => None,
+                });
+
+        let cascades = match shadow_caster {
+            Some((_, direction)) => self.shadow_map.cascades(direction.into(), camera),
+            None => Ok(Vec::new()),
+        };
+
+        match &cascades {
+            Ok(cascades) => {
+                self.shadow_map.update(queue, cascades);
+                queue.write_buffer(
+                    &self.shadow_uniform_buffer,
+                    0,
+                    bytemuck::cast_slice(&[ShadowUniform::new(
+                        cascades,
+                        shadow_caster.map(|(index, _)| index),
+                        &self.shadow_map.settings,
+                    )]),
+                );
+            }
+            Err(error) => log::error!("Keeping the previous shadow cascades: {:#}", error),
+        }
+
+        // Point lights casting shadows get a slot in the cube maps while there are slots left
+        let mut point_shadow_positions = Vec::new();
+        let lights = lights
+            .into_iter()
+            .map(|(light, position)| {
+                let raw = LightRaw::new(light, position);
+                let has_slot = point_shadow_positions.len() < self.point_shadow_maps.capacity();
+
+                match light.kind {
+                    LightKind::Point { .. } if light.cast_shadows && has_slot => {
+                        point_shadow_positions.push(cgmath::Point3::from_vec(position.into()));
+                        raw.with_shadow_index(point_shadow_positions.len() - 1)
+                    }
+                    _ => raw,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let point_shadows = self.point_shadow_maps.update(
+            queue,
+            &point_shadow_positions,
+            &self.shadow_map.settings,
+        );
+        queue.write_buffer(
+            &self.point_shadows_buffer,
+            0,
+            bytemuck::cast_slice(&[point_shadows]),
+        );
+
+        queue.write_buffer(
+            &self.lights_buffer,
+            0,
+            bytemuck::cast_slice(&[LightsHeader::new(lights.len())]),
+        );
+        queue.write_buffer(
+            &self.lights_buffer,
+            size_of::<LightsHeader>() as wgpu::BufferAddress,
+            bytemuck::cast_slice(&lights),
+        );
+
+        let mut instances = Vec::new();
+        let batches = batch_entities(entities, &mut instances);
+
+        // Grows geometrically, like the locals
+        let capacity = self.instance_buffer.size() / size_of::<InstanceRaw>() as u64;
+        if instances.len() as u64 > capacity {
+            let capacity = (instances.len() as u64).max(capacity * 2);
+            self.instance_buffer = create_instance_buffer(device, capacity);
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+
+        // The locals of every mesh of each batch go in their own slot of the same buffer
+        let locals = batches
+            .iter()
+            .flat_map(|batch| {
+                let model = &batch.model;
+                model
+                    .meshes
+                    .iter()
+                    .map(|(_, material_index)| Locals::new(&model.materials[*material_index]))
+            })
+            .collect::<Vec<_>>();
+        self.local_uniforms.write(device, queue, &locals);
+
+        let mesh_count = locals.len();
+        let mut draws = Vec::with_capacity(mesh_count);
+        self.joint_buffers.resize_with(batches.len(), || None);
+        self.morph_weight_buffers.resize_with(mesh_count, || None);
+
+        // Pose the skeletons of the batches, and the joints of the skinned ones
+        let mut poses = Vec::with_capacity(batches.len());
+        for (batch, joint_buffer) in batches.iter().zip(&mut self.joint_buffers) {
+            let Some(skin) = &batch.model.skin else {
+                *joint_buffer = None;
+                poses.push(None);
+                continue;
+            };
+
+            let pose = match &batch.entity.animation {
+                Some(animation) => animation.pose(&batch.model.animations, skin),
+                None => skin.rest_pose(),
+            };
+
+            if skin.joints.is_empty() {
+                *joint_buffer = None;
+            } else {
+                let joint_matrices = skin
+                    .joint_matrices(&pose.transforms)
+                    .into_iter()
+                    .map(Into::into)
+                    .collect::<Vec<[[f32; 4]; 4]>>();
+
+                let joint_buffer = storage_buffer(
+                    joint_buffer,
+                    device,
+                    "[PBR] Joints",
+                    size_of_val(&joint_matrices[..]) as wgpu::BufferAddress,
+                );
+                queue.write_buffer(joint_buffer, 0, bytemuck::cast_slice(&joint_matrices));
+            }
+
+            poses.push(Some(pose));
+        }
+
+        let placeholders = self
+            .placeholder_textures
+            .get_or_insert_with(|| PlaceholderTextures {
+                white: Handle::new(utils::white_texture(device, queue, "[PBR] White")),
+                flat_normal: Handle::new(utils::flat_normal_texture(
+                    device,
+                    queue,
+                    "[PBR] Flat normal",
+                )),
+            })
+            .clone();
+
+        let mut index = 0;
+        for (batch_index, batch) in batches.iter().enumerate() {
+            let (model, entity) = (&batch.model, batch.entity);
+
+            let distance = camera.get_position().distance2(cgmath::Point3::from_vec(
+                (&entity.transform.position).into(),
+            ));
+
+            for (mesh_index, (mesh, material_index)) in model.meshes.iter().enumerate() {
+                let material = &model.materials[*material_index];
+
+                // The entity weights win over the animated ones, which win over the defaults
+                let weights_buffer = &mut self.morph_weight_buffers[index];
+                match &mesh.morph_targets {
+                    Some(morph_targets) => {
+                        let animated = morph_targets.node.and_then(|node| {
+                            let pose = poses[batch_index].as_ref()?;
+                            pose.weights.get(node)
+                        });
+                        let mut weights = entity
+                            .morph_weights
+                            .as_ref()
+                            .or(animated)
+                            .unwrap_or(&morph_targets.default_weights)
+                            .clone();
+                        weights.resize(morph_targets.count, 0.0);
+
+                        let weights_buffer = storage_buffer(
+                            weights_buffer,
+                            device,
+                            "[PBR] Morph weights",
+                            MORPH_WEIGHTS_HEADER_SIZE
+                                + size_of_val(&weights[..]) as wgpu::BufferAddress,
+                        );
+                        let header = MorphWeightsHeader {
+                            target_count: morph_targets.count as u32,
+                            vertex_count: morph_targets.vertex_count as u32,
+                        };
+                        queue.write_buffer(weights_buffer, 0, bytemuck::cast_slice(&[header]));
+                        queue.write_buffer(
+                            weights_buffer,
+                            MORPH_WEIGHTS_HEADER_SIZE,
+                            bytemuck::cast_slice(&weights),
+                        );
+                    }
+                    None => *weights_buffer = None,
+                }
+
+                let uniforms = self.local_uniforms.buffer();
+                let texture = material.texture.get();
+                let optional_texture =
+                    |texture: &Option<Handle<Texture>>, placeholder: &Handle<Texture>| {
+                        texture.as_ref().unwrap_or(placeholder).get()
+                    };
+                let white = &placeholders.white;
+                let metallic_roughness =
+                    optional_texture(&material.metallic_roughness_texture, white);
+                let occlusion = optional_texture(&material.occlusion_texture, white);
+                let emissive = optional_texture(&material.emissive_texture, white);
+                let normal = optional_texture(&material.normal_texture, &placeholders.flat_normal);
+                let joints = self.joint_buffers[batch_index]
+                    .as_ref()
+                    .unwrap_or(&self.identity_joints);
+                let (morph_deltas, morph_weights) = match &mesh.morph_targets {
+                    Some(morph_targets) => (
+                        &morph_targets.buffer,
+                        self.morph_weight_buffers[index].as_ref().unwrap(),
+                    ),
+                    None => (&self.no_morph_targets, &self.no_morph_targets),
+                };
+
+                let resources = LocalResources {
+                    uniforms: uniforms.global_id(),
+                    texture: texture.view.global_id(),
+                    sampler: texture.sampler.global_id(),
+                    metallic_roughness: metallic_roughness.view.global_id(),
+                    occlusion: occlusion.view.global_id(),
+                    emissive: emissive.view.global_id(),
+                    normal: normal.view.global_id(),
+                    joints: joints.global_id(),
+                    morph_deltas: morph_deltas.global_id(),
+                    morph_weights: morph_weights.global_id(),
+                };
+
+                self.local_bind_groups.entry(resources).or_insert_with(|| {
+                    device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("[PBR] Locals"),
+                        layout: &self.local_bind_group_layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: self.local_uniforms.binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::TextureView(&texture.view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 3,
+                                resource: joints.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 4,
+                                resource: morph_deltas.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 5,
+                                resource: morph_weights.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 6,
+                                resource: wgpu::BindingResource::TextureView(
+                                    &metallic_roughness.view,
+                                ),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 7,
+                                resource: wgpu::BindingResource::TextureView(&occlusion.view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 8,
+                                resource: wgpu::BindingResource::TextureView(&emissive.view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 9,
+                                resource: wgpu::BindingResource::TextureView(&normal.view),
+                            },
+                        ],
+                    })
+                });
+                let features = ShaderFeatures {
+                    skinned: self.joint_buffers[batch_index].is_some(),
+                    morph_targets: mesh.morph_targets.is_some(),
+                    transparent: material.is_transparent(),
+                    clustered: self.render_path == RenderPath::Clustered,
+                };
+                let draw = MeshDraw {
+                    model: model.clone(),
+                    mesh: mesh_index,
+                    instances: batch.instances.clone(),
+                    resources,
+                    offset: self.local_uniforms.offset(index),
+                    features,
+                };
+                draws.push((distance, draw));
+
+                index += 1;
+            }
+        }
+
+        // Transparent meshes go last, blended from back to front. Opaque ones keep their order
+        draws.sort_by(|(a_distance, a), (b_distance, b)| {
+            let transparent = a.features.transparent.cmp(&b.features.transparent);
+            match a.features.transparent && b.features.transparent {
+                true => transparent.then(b_distance.total_cmp(a_distance)),
+                false => transparent,
+            }
+        });
+        self.draws = draws.into_iter().map(|(_, draw)| draw).collect();
+
+        // Models, materials and buffers change between frames, so drop the unused bind groups
+        let used = self
+            .draws
+            .iter()
+            .map(|draw| draw.resources)
+            .collect::<HashSet<_>>();
+        self.local_bind_groups
+            .retain(|resources, _| used.contains(resources));
+
+        // Each permutation is built the first time a mesh needs it
+        let features = self
+            .draws
+            .iter()
+            .map(|draw| draw.features)
+            .collect::<HashSet<_>>();
+        for features in features {
+            self.prepare_pipelines(device, features);
+        }
+
+        if let Ok(cascades) = cascades {
+            self.cascade_count = cascades.len();
+        }
+        self.point_shadow_count = point_shadow_positions.len();
+    }
+
+    fn record<'a>(&'a self, graph: &mut FrameGraph<'a>, target: ResourceId) {
+        let shadow_map = graph.import_texture("Shadow map", &self.shadow_map.texture.view);
+        let point_shadow_maps =
+            graph.import_texture("Point shadow maps", &self.point_shadow_maps.texture.view);
+
+        for cascade in 0..self.cascade_count {
+            graph
+                .add_node("Shadow cascade")
+                .write(shadow_map)
+                .record(move |encoder, _| {
+                    let mut shadow_pass = self.shadow_map.begin(encoder, cascade);
+                    self.draw_batches(&mut shadow_pass, &self.shadow_map.pipelines);
+                });
+        }
+
+        for slot in 0..self.point_shadow_count {
+            for face in 0..CUBE_FACES {
+                graph
+                    .add_node("Point shadow face")
+                    .write(point_shadow_maps)
+                    .record(move |encoder, _| {
+                        let mut shadow_pass = self.point_shadow_maps.begin(encoder, slot, face);
+                        self.draw_batches(&mut shadow_pass, &self.point_shadow_maps.pipelines);
+                    });
+            }
+        }
+
+        let shadow_maps = [shadow_map, point_shadow_maps];
+
+        match self.render_path {
+            RenderPath::Forward => self.record_forward(graph, target, &shadow_maps),
+            RenderPath::Deferred => self.record_deferred(graph, target, shadow_maps),
+            RenderPath::Clustered => {
+                let light_lists = self.record_culling(graph);
+                self.record_forward(graph, target, &[shadow_map, point_shadow_maps, light_lists]);
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::mem::size_of;
+
+use anyhow::Context;
+use cgmath::{Deg, Matrix4, Point3, Vector3};
+
+use super::{
+    reflection::ShaderInterface,
+    shader::{builtin_interface, ShaderFeatures, ShaderLibrary, ShaderPipelines},
+    shadow::ShadowSettings,
+};
+use crate::{
+    camera::OPENGL_TO_WGPU_MATRIX,
+    components::{InstanceRaw, Vertex},
+    texture::Texture,
+};
+
+/// Most point lights the lighting shader can sample shadows for.
+pub const MAX_POINT_SHADOWS: usize = 4;
+
+/// Shadow map layers rendered for each point light.
+pub const CUBE_FACES: usize = 6;
+
+/// Cube faces in the usual +X, -X, +Y, -Y, +Z, -Z order, as view directions and up vectors.
+const FACES: [(Vector3<f32>, Vector3<f32>); CUBE_FACES] = [
+    (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0)),
+    (Vector3::new(-1.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0)),
+    (Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)),
+    (Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 0.0, -1.0)),
+    (Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, -1.0, 0.0)),
+    (Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, -1.0, 0.0)),
+];
+
+const FACE_ZNEAR: f32 = 0.05;
+
+/// Data the depth pass of one cube face needs.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct FaceUniform {
+    view_proj: [[f32; 4]; 4],
+    /// Light position in `xyz` and range in `w`.
+    light_position: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PointShadowRaw {
+    face_view_proj: [[[f32; 4]; 4]; CUBE_FACES],
+    /// Light position in `xyz` and range in `w`.
+    position: [f32; 4],
+    texel_size: f32,
+    bias: f32,
+    _padding: [f32; 2],
+}
+
+/// Data the lighting shader needs to sample the point light shadow maps.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointShadowsUniform {
+    shadows: [PointShadowRaw; MAX_POINT_SHADOWS],
+}
+
+/// Depth pipeline and layered target that render the scene around point lights, six layers per
+/// light. Layers store the distance to the light divided by the range instead of the projected
+/// depth, so every face compares the same way.
+pub struct PointShadowMaps {
+    pub texture: Texture,
+
+    layer_views: Vec<wgpu::TextureView>,
+    face_uniforms: Vec<(wgpu::Buffer, wgpu::BindGroup)>,
+
+    pipeline_layout: wgpu::PipelineLayout,
+    pub(super) pipelines: ShaderPipelines,
+}
+
+impl PointShadowMaps {
+    pub fn new(
+        device: &wgpu::Device,
+        local_bind_group_layout: &wgpu::BindGroupLayout,
+        local_interface: &ShaderInterface,
+        settings: &ShadowSettings,
+        shader_library: ShaderLibrary,
+    ) -> anyhow::Result<Self> {
+        let face_size = size_of::<FaceUniform>() as wgpu::BufferAddress;
+        let layers = (settings.point_shadow_count() * CUBE_FACES) as u32;
+
+        let interface = reflect_shader(local_interface)
+            .context("The point shadow shader doesn't match the pass")?;
+        let face_bind_group_layout = interface.bind_group_layout(device, 0, "[PointShadow] Face");
+
+        let face_uniforms = (0..layers)
+            .map(|_| {
+                let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("[PointShadow] Face"),
+                    size: face_size,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("[PointShadow] Face"),
+                    layout: &face_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                });
+
+                (buffer, bind_group)
+            })
+            .collect();
+
+        // Some backends treat square textures with a multiple of six layers as cube maps, which
+        // can't be sampled as 2D arrays, so a spare layer is allocated
+        let texture = Texture::create_shadow_map(
+            device,
+            settings.point_resolution,
+            layers + 1,
+            "point_shadow_maps",
+        );
+        let layer_views = (0..layers)
+            .map(|layer| texture.create_layer_view(layer))
+            .collect();
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Point shadow pipeline layout"),
+            bind_group_layouts: &[&face_bind_group_layout, local_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Ok(Self {
+            texture,
+            layer_views,
+            face_uniforms,
+            pipeline_layout,
+            pipelines: ShaderPipelines::new("point_shadow.wgsl", shader_library, interface),
+        })
+    }
+
+    /// Builds the pipeline for meshes with `features`, unless it's built already.
+    pub fn prepare(&mut self, device: &wgpu::Device, features: ShaderFeatures) {
+        let layout = &self.pipeline_layout;
+        self.pipelines.prepare(device, features, |module, _| {
+            create_pipeline(device, layout, module)
+        });
+    }
+
+    /// Rebuilds the pipelines when their watched shader changed.
+    pub fn reload_shader(&mut self, device: &wgpu::Device) {
+        let layout = &self.pipeline_layout;
+        self.pipelines
+            .reload(device, |module, _| create_pipeline(device, layout, module));
+    }
+
+    /// Number of point lights that can cast shadows at once.
+    pub fn capacity(&self) -> usize {
+        self.face_uniforms.len() / CUBE_FACES
+    }
+
+    /// Writes the face matrices of each shadow casting light, in slot order, and returns what the
+    /// lighting shader needs to sample them.
+    pub fn update(
+        &self,
+        queue: &wgpu::Queue,
+        positions: &[Point3<f32>],
+        settings: &ShadowSettings,
+    ) -> PointShadowsUniform {
+        let mut uniform = PointShadowsUniform {
+            shadows: bytemuck::Zeroable::zeroed(),
+        };
+
+        for (slot, position) in positions.iter().take(self.capacity()).enumerate() {
+            let light_position = [position.x, position.y, position.z, settings.point_range];
+            let shadow = &mut uniform.shadows[slot];
+            shadow.position = light_position;
+            shadow.texel_size = 1.0 / settings.point_resolution as f32;
+            shadow.bias = settings.point_bias;
+
+            for face in 0..CUBE_FACES {
+                let view_proj = point_light_view_proj(*position, face, settings.point_range).into();
+                shadow.face_view_proj[face] = view_proj;
+
+                let (buffer, _) = &self.face_uniforms[slot * CUBE_FACES + face];
+                queue.write_buffer(
+                    buffer,
+                    0,
+                    bytemuck::cast_slice(&[FaceUniform {
+                        view_proj,
+                        light_position,
+                    }]),
+                );
+            }
+        }
+
+        uniform
+    }
+
+    /// Starts the depth pass of one cube face with the face bound. The caller sets the pipeline of
+    /// each mesh from `pipelines`, binds the locals in group 1 and issues the draws.
+    pub fn begin<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        slot: usize,
+        face: usize,
+    ) -> wgpu::RenderPass<'a> {
+        let layer = slot * CUBE_FACES + face;
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Point shadow pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.layer_views[layer],
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_bind_group(0, &self.face_uniforms[layer].1, &[]);
+
+        render_pass
+    }
+}
+
+/// 90° perspective view projection looking down one cube face from a point light.
+pub fn point_light_view_proj(position: Point3<f32>, face: usize, range: f32) -> Matrix4<f32> {
+    let (direction, up) = FACES[face];
+    let view = Matrix4::look_to_rh(position, direction, up);
+    let projection = cgmath::perspective(Deg(90.0), 1.0, FACE_ZNEAR, range);
+
+    OPENGL_TO_WGPU_MATRIX * projection * view
+}
+
+/// Resources the point shadow shader binds: the cube face, and the locals of the pass in
+/// `local_interface`.
+fn reflect_shader(local_interface: &ShaderInterface) -> anyhow::Result<ShaderInterface> {
+    let interface = builtin_interface("point_shadow.wgsl")?.group(0);
+    interface.check_size::<FaceUniform>("face")?;
+
+    interface.merge(local_interface.group(1))
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Point shadow pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc(), InstanceRaw::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
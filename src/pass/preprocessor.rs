@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, bail, Context};
+
+/// Expands the directives of the WGSL file `file_name`, reading it and the files it includes
+/// with `load`:
+///
+/// - `#include "file.wgsl"` pastes a file in. Each file is pasted once per shader, so modules
+///   can include what they use without clashing.
+/// - `#define NAME` defines a name for the rest of the shader, like the ones in `defines`.
+/// - `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif` keep or drop the lines between them.
+///
+/// Directives take a line of their own, starting with `#`.
+pub fn preprocess(
+    file_name: &str,
+    defines: &[&str],
+    load: impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<String> {
+    let mut preprocessor = Preprocessor {
+        load,
+        defines: defines.iter().map(|name| name.to_string()).collect(),
+        included: HashSet::new(),
+        output: String::new(),
+    };
+    preprocessor.include(file_name)?;
+
+    Ok(preprocessor.output)
+}
+
+struct Preprocessor<F> {
+    load: F,
+    defines: HashSet<String>,
+    included: HashSet<String>,
+    output: String,
+}
+
+/// `#ifdef` or `#ifndef` block the preprocessor is in.
+struct Condition {
+    /// Whether its lines are kept, regardless of the enclosing blocks.
+    holds: bool,
+    in_else: bool,
+}
+
+impl<F: Fn(&str) -> Option<String>> Preprocessor<F> {
+    fn include(&mut self, file_name: &str) -> anyhow::Result<()> {
+        if !self.included.insert(file_name.to_string()) {
+            return Ok(());
+        }
+
+        let source = (self.load)(file_name)
+            .ok_or_else(|| anyhow!("Couldn't find WGSL module {:?}", file_name))?;
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        for (number, line) in source.lines().enumerate() {
+            let location = || format!("{}:{}", file_name, number + 1);
+            let kept = conditions.iter().all(|condition| condition.holds);
+
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if kept {
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                }
+                continue;
+            };
+
+            let (name, argument) = directive
+                .split_once(char::is_whitespace)
+                .unwrap_or((directive, ""));
+            let argument = argument.trim();
+
+            match name {
+                "ifdef" | "ifndef" => {
+                    if argument.is_empty() {
+                        bail!("{}: #{} needs a name", location(), name);
+                    }
+                    let defined = self.defines.contains(argument);
+                    conditions.push(Condition {
+                        holds: defined == (name == "ifdef"),
+                        in_else: false,
+                    });
+                }
+                "else" => {
+                    let condition = conditions
+                        .last_mut()
+                        .filter(|condition| !condition.in_else)
+                        .with_context(|| format!("{}: #else without #ifdef", location()))?;
+                    condition.holds = !condition.holds;
+                    condition.in_else = true;
+                }
+                "endif" => {
+                    conditions
+                        .pop()
+                        .with_context(|| format!("{}: #endif without #ifdef", location()))?;
+                }
+                "define" if kept => {
+                    if argument.is_empty() {
+                        bail!("{}: #define needs a name", location());
+                    }
+                    self.defines.insert(argument.to_string());
+                }
+                "include" if kept => {
+                    let included = argument
+                        .strip_prefix('"')
+                        .and_then(|argument| argument.strip_suffix('"'))
+                        .with_context(|| {
+                            format!("{}: #include needs a quoted file name", location())
+                        })?;
+                    self.include(included).with_context(|| {
+                        format!("{}: in the include of {:?}", location(), included)
+                    })?;
+                }
+                "define" | "include" => {}
+                _ => bail!("{}: unknown directive #{}", location(), name),
+            }
+        }
+
+        if !conditions.is_empty() {
+            bail!("{}: #ifdef without #endif", file_name);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,351 @@
+use std::{collections::BTreeMap, mem::size_of};
+
+use anyhow::{bail, Context};
+
+use super::shader::validate;
+
+/// Resources a shader binds, reflected from its naga module so the bind group layouts are made
+/// from what the shader declares instead of being kept in sync by hand.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderInterface {
+    bindings: BTreeMap<(u32, u32), Binding>,
+}
+
+#[derive(Clone, Debug)]
+struct Binding {
+    /// Name of the global variable, and label of the shader declaring it.
+    name: String,
+    shader: String,
+    ty: wgpu::BindingType,
+    /// Stages whose entry points use the binding.
+    visibility: wgpu::ShaderStages,
+}
+
+impl Binding {
+    fn describe(&self, (group, binding): (u32, u32)) -> String {
+        format!(
+            "`{}` at @group({}) @binding({}) of {}",
+            self.name, group, binding, self.shader
+        )
+    }
+}
+
+impl ShaderInterface {
+    /// Validates the WGSL `source` and reflects its bindings.
+    pub fn from_wgsl(label: &str, source: &str) -> anyhow::Result<Self> {
+        let (module, info) = validate(label, source).map_err(anyhow::Error::msg)?;
+        Self::reflect(label, &module, &info)
+    }
+
+    pub fn reflect(
+        label: &str,
+        module: &naga::Module,
+        info: &naga::valid::ModuleInfo,
+    ) -> anyhow::Result<Self> {
+        let mut bindings = BTreeMap::new();
+
+        for (handle, variable) in module.global_variables.iter() {
+            let Some(slot) = &variable.binding else {
+                continue;
+            };
+            let name = variable.name.clone().unwrap_or_default();
+            let ty = binding_type(module, variable)
+                .with_context(|| format!("Can't bind `{}` of {}", name, label))?;
+
+            let visibility = module
+                .entry_points
+                .iter()
+                .enumerate()
+                .filter(|&(index, _)| !info.get_entry_point(index)[handle].is_empty())
+                .fold(wgpu::ShaderStages::NONE, |visibility, (_, entry_point)| {
+                    visibility | stage(entry_point.stage)
+                });
+
+            bindings.insert(
+                (slot.group, slot.binding),
+                Binding {
+                    name,
+                    shader: label.to_string(),
+                    ty,
+                    visibility,
+                },
+            );
+        }
+
+        Ok(Self { bindings })
+    }
+
+    /// Bindings of `group` alone.
+    pub fn group(&self, group: u32) -> Self {
+        Self {
+            bindings: self
+                .bindings
+                .iter()
+                .filter(|((binding_group, _), _)| *binding_group == group)
+                .map(|(slot, binding)| (*slot, binding.clone()))
+                .collect(),
+        }
+    }
+
+    /// Adds the bindings of a shader that shares bind groups with this one, which have to be the
+    /// same resources wherever both declare one.
+    pub fn merge(mut self, other: Self) -> anyhow::Result<Self> {
+        for (slot, binding) in other.bindings {
+            match self.bindings.get_mut(&slot) {
+                Some(existing) => {
+                    if !same_resource(&existing.ty, &binding.ty) {
+                        bail!(
+                            "{} and {} are different resources",
+                            existing.describe(slot),
+                            binding.describe(slot)
+                        );
+                    }
+                    existing.visibility |= binding.visibility;
+                }
+                None => {
+                    self.bindings.insert(slot, binding);
+                }
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Binds the uniform buffer `name` with a dynamic offset, which the shader can't tell.
+    pub fn with_dynamic_offset(mut self, name: &str) -> anyhow::Result<Self> {
+        let binding = self.find_mut(name)?;
+        match &mut binding.ty {
+            wgpu::BindingType::Buffer {
+                has_dynamic_offset, ..
+            } => *has_dynamic_offset = true,
+            _ => bail!("`{}` of {} isn't a buffer", binding.name, binding.shader),
+        }
+
+        Ok(self)
+    }
+
+    /// Binds the float texture `name` as unfilterable, for formats like `R32Float` that the
+    /// shader only loads from.
+    pub fn with_unfilterable(mut self, name: &str) -> anyhow::Result<Self> {
+        let binding = self.find_mut(name)?;
+        match &mut binding.ty {
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable },
+                ..
+            } => *filterable = false,
+            _ => bail!(
+                "`{}` of {} isn't a float texture",
+                binding.name,
+                binding.shader
+            ),
+        }
+
+        Ok(self)
+    }
+
+    /// Checks that the buffer bound as `name` has the size of `T`, the Rust struct written to it.
+    pub fn check_size<T>(&self, name: &str) -> anyhow::Result<()> {
+        let type_name = std::any::type_name::<T>();
+        let type_name = type_name.rsplit("::").next().unwrap_or(type_name);
+        self.check_size_of(name, type_name, size_of::<T>())
+    }
+
+    /// Like `check_size`, for buffers holding more than one Rust value. `rust_type` names what
+    /// `size` is the size of in the error.
+    pub fn check_size_of(&self, name: &str, rust_type: &str, size: usize) -> anyhow::Result<()> {
+        let (slot, binding) = self.find(name)?;
+        let wgpu::BindingType::Buffer {
+            min_binding_size: Some(shader_size),
+            ..
+        } = binding.ty
+        else {
+            bail!("{} isn't a buffer", binding.describe(slot));
+        };
+
+        if shader_size.get() != size as u64 {
+            bail!(
+                "`{}` is {} bytes, but {} is {} bytes",
+                rust_type,
+                size,
+                binding.describe(slot),
+                shader_size
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Checks that `shader` only uses resources of this interface, in the stages they're visible
+    /// to, so it runs with the layouts made from it.
+    pub fn check_compatible(&self, shader: &Self) -> anyhow::Result<()> {
+        for (slot, used) in &shader.bindings {
+            if used.visibility.is_empty() {
+                continue;
+            }
+
+            let Some(bound) = self.bindings.get(slot) else {
+                bail!("{} isn't bound by the renderer", used.describe(*slot));
+            };
+            if !same_resource(&bound.ty, &used.ty) {
+                bail!(
+                    "{} doesn't match {} the renderer binds",
+                    used.describe(*slot),
+                    bound.describe(*slot)
+                );
+            }
+            if !bound.visibility.contains(used.visibility) {
+                bail!(
+                    "{} is used in {:?}, but it's only visible to {:?}",
+                    used.describe(*slot),
+                    used.visibility,
+                    bound.visibility
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn layout_entries(&self, group: u32) -> Vec<wgpu::BindGroupLayoutEntry> {
+        self.bindings
+            .iter()
+            .filter(|((binding_group, _), _)| *binding_group == group)
+            .map(|(&(_, binding), declared)| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: declared.visibility,
+                ty: declared.ty,
+                count: None,
+            })
+            .collect()
+    }
+
+    pub fn bind_group_layout(
+        &self,
+        device: &wgpu::Device,
+        group: u32,
+        label: &str,
+    ) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &self.layout_entries(group),
+        })
+    }
+
+    fn find(&self, name: &str) -> anyhow::Result<((u32, u32), &Binding)> {
+        self.bindings
+            .iter()
+            .find(|(_, binding)| binding.name == name)
+            .map(|(slot, binding)| (*slot, binding))
+            .with_context(|| format!("No shader binds `{}`", name))
+    }
+
+    fn find_mut(&mut self, name: &str) -> anyhow::Result<&mut Binding> {
+        self.bindings
+            .values_mut()
+            .find(|binding| binding.name == name)
+            .with_context(|| format!("No shader binds `{}`", name))
+    }
+}
+
+fn binding_type(
+    module: &naga::Module,
+    variable: &naga::GlobalVariable,
+) -> anyhow::Result<wgpu::BindingType> {
+    let inner = &module.types[variable.ty].inner;
+    let buffer = |ty| wgpu::BindingType::Buffer {
+        ty,
+        has_dynamic_offset: false,
+        // Runtime sized arrays count as one element
+        min_binding_size: wgpu::BufferSize::new(inner.size(module.to_ctx()) as u64),
+    };
+
+    Ok(match variable.space {
+        naga::AddressSpace::Uniform => buffer(wgpu::BufferBindingType::Uniform),
+        naga::AddressSpace::Storage { access } => buffer(wgpu::BufferBindingType::Storage {
+            read_only: !access.contains(naga::StorageAccess::STORE),
+        }),
+        naga::AddressSpace::Handle => match *inner {
+            naga::TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            } => {
+                let (sample_type, multisampled) = match class {
+                    naga::ImageClass::Sampled { kind, multi } => {
+                        let sample_type = match kind {
+                            naga::ScalarKind::Float => {
+                                wgpu::TextureSampleType::Float { filterable: true }
+                            }
+                            naga::ScalarKind::Sint => wgpu::TextureSampleType::Sint,
+                            naga::ScalarKind::Uint => wgpu::TextureSampleType::Uint,
+                            kind => bail!("Textures of {:?} aren't supported", kind),
+                        };
+                        (sample_type, multi)
+                    }
+                    naga::ImageClass::Depth { multi } => (wgpu::TextureSampleType::Depth, multi),
+                    naga::ImageClass::Storage { .. } => bail!("Storage textures aren't supported"),
+                };
+
+                wgpu::BindingType::Texture {
+                    sample_type,
+                    view_dimension: view_dimension(dim, arrayed),
+                    multisampled,
+                }
+            }
+            naga::TypeInner::Sampler { comparison } => wgpu::BindingType::Sampler(if comparison {
+                wgpu::SamplerBindingType::Comparison
+            } else {
+                wgpu::SamplerBindingType::Filtering
+            }),
+            ref inner => bail!("{:?} isn't a resource", inner),
+        },
+        space => bail!("{:?} variables aren't bound", space),
+    })
+}
+
+fn view_dimension(dim: naga::ImageDimension, arrayed: bool) -> wgpu::TextureViewDimension {
+    match (dim, arrayed) {
+        (naga::ImageDimension::D1, _) => wgpu::TextureViewDimension::D1,
+        (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+        (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+        (naga::ImageDimension::D3, _) => wgpu::TextureViewDimension::D3,
+        (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+        (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+    }
+}
+
+fn stage(stage: naga::ShaderStage) -> wgpu::ShaderStages {
+    match stage {
+        naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+        naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+        naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+    }
+}
+
+/// Whether two bindings hold the same kind of resource. Dynamic offsets and filtering only matter
+/// to the layout, shaders can't tell them apart.
+fn same_resource(a: &wgpu::BindingType, b: &wgpu::BindingType) -> bool {
+    let normalized = |ty: &wgpu::BindingType| match *ty {
+        wgpu::BindingType::Buffer {
+            ty,
+            min_binding_size,
+            ..
+        } => wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size,
+        },
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { .. },
+            view_dimension,
+            multisampled,
+        } => wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension,
+            multisampled,
+        },
+        ty => ty,
+    };
+
+    normalized(a) == normalized(b)
+}
@@ -0,0 +1,314 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use anyhow::Context;
+
+use super::{preprocessor::preprocess, reflection::ShaderInterface};
+use crate::assets::{Assets, Handle};
+
+/// WGSL files the shaders are assembled from, with the sources built into the binary.
+const MODULES: &[(&str, &str)] = &[
+    ("shader.wgsl", include_str!("shaders/shader.wgsl")),
+    ("shadow.wgsl", include_str!("shaders/shadow.wgsl")),
+    (
+        "point_shadow.wgsl",
+        include_str!("shaders/point_shadow.wgsl"),
+    ),
+    ("globals.wgsl", include_str!("shaders/globals.wgsl")),
+    ("locals.wgsl", include_str!("shaders/locals.wgsl")),
+    ("lights.wgsl", include_str!("shaders/lights.wgsl")),
+    ("brdf.wgsl", include_str!("shaders/brdf.wgsl")),
+    ("lighting.wgsl", include_str!("shaders/lighting.wgsl")),
+    ("shadows.wgsl", include_str!("shaders/shadows.wgsl")),
+    ("skinning.wgsl", include_str!("shaders/skinning.wgsl")),
+    ("morph.wgsl", include_str!("shaders/morph.wgsl")),
+    ("vertex.wgsl", include_str!("shaders/vertex.wgsl")),
+    ("gbuffer.wgsl", include_str!("shaders/gbuffer.wgsl")),
+    (
+        "deferred_lighting.wgsl",
+        include_str!("shaders/deferred_lighting.wgsl"),
+    ),
+    ("clusters.wgsl", include_str!("shaders/clusters.wgsl")),
+    (
+        "cluster_lights.wgsl",
+        include_str!("shaders/cluster_lights.wgsl"),
+    ),
+];
+
+/// Material and vertex features of a mesh, and how the pass lights it, each defining a name the
+/// shaders check with `#ifdef`. Meshes with the same features are drawn with the same shader
+/// permutation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ShaderFeatures {
+    /// Vertices blended by joint matrices, `SKINNED`.
+    pub skinned: bool,
+    /// Vertices displaced by morph targets, `MORPH_TARGETS`.
+    pub morph_targets: bool,
+    /// Material blended over what's behind it, `TRANSPARENT`.
+    pub transparent: bool,
+    /// Lit by the lights of its cluster only, `CLUSTERED`.
+    pub clustered: bool,
+}
+
+impl ShaderFeatures {
+    /// Every feature, whose permutation binds every resource of a shader.
+    pub const ALL: Self = Self {
+        skinned: true,
+        morph_targets: true,
+        transparent: true,
+        clustered: true,
+    };
+
+    pub fn defines(&self) -> Vec<&'static str> {
+        [
+            (self.skinned, "SKINNED"),
+            (self.morph_targets, "MORPH_TARGETS"),
+            (self.transparent, "TRANSPARENT"),
+            (self.clustered, "CLUSTERED"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect()
+    }
+}
+
+/// WGSL modules the shaders are assembled from. They're the ones built into the binary unless
+/// `watch` reads them from a directory, where `assets` reloads them when they change.
+#[derive(Clone, Default)]
+pub struct ShaderLibrary {
+    /// Sources read from disk, in the order of `MODULES`.
+    watched: Option<Vec<Handle<String>>>,
+}
+
+impl ShaderLibrary {
+    pub fn watch(assets: &Assets, directory: &Path) -> anyhow::Result<Self> {
+        let watched = MODULES
+            .iter()
+            .map(|(file_name, _)| {
+                let path = directory.join(file_name);
+                let path = path
+                    .canonicalize()
+                    .with_context(|| format!("Couldn't find shader {:?}", path))?;
+                assets.load_shader(&path.to_string_lossy())
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            watched: Some(watched),
+        })
+    }
+
+    /// Changes whenever one of the watched modules is reloaded.
+    fn version(&self) -> u64 {
+        self.watched
+            .iter()
+            .flatten()
+            .map(|source| source.version())
+            .sum()
+    }
+
+    fn snapshot(&self) -> Sources {
+        Sources(
+            self.watched
+                .as_ref()
+                .map(|watched| watched.iter().map(Handle::get).collect()),
+        )
+    }
+}
+
+/// Sources of the modules at one point, the builtin ones when it's `None`.
+#[derive(Clone, Default)]
+struct Sources(Option<Vec<Arc<String>>>);
+
+impl Sources {
+    fn get(&self, file_name: &str) -> Option<String> {
+        let index = MODULES.iter().position(|(name, _)| *name == file_name)?;
+        Some(match &self.0 {
+            Some(sources) => sources[index].to_string(),
+            None => MODULES[index].1.to_string(),
+        })
+    }
+}
+
+/// Resources the builtin `file_name` binds with every feature enabled, which the bind group
+/// layouts of its pipelines are made from.
+pub fn builtin_interface(file_name: &str) -> anyhow::Result<ShaderInterface> {
+    let source = preprocess(file_name, &ShaderFeatures::ALL.defines(), |name| {
+        Sources::default().get(name)
+    })?;
+    ShaderInterface::from_wgsl(file_name, &source)
+}
+
+/// Render or compute pipelines of a shader, built the first time a permutation is needed and
+/// cached. When a module of the library changes, every cached permutation is rebuilt, and they
+/// all stay as they were if any of them has errors.
+pub struct ShaderPipelines<P = wgpu::RenderPipeline> {
+    file_name: &'static str,
+    library: ShaderLibrary,
+    /// Resources the pipeline layout binds, which the permutations have to stick to.
+    interface: ShaderInterface,
+    /// Sources the cached permutations were built from, and the library version they're from.
+    sources: Sources,
+    version: u64,
+    permutations: HashMap<ShaderFeatures, P>,
+}
+
+impl<P> ShaderPipelines<P> {
+    pub fn new(
+        file_name: &'static str,
+        library: ShaderLibrary,
+        interface: ShaderInterface,
+    ) -> Self {
+        Self {
+            file_name,
+            interface,
+            sources: library.snapshot(),
+            version: library.version(),
+            library,
+            permutations: HashMap::new(),
+        }
+    }
+
+    /// Switches to the modules of `library`, dropping the permutations built so far.
+    pub fn set_library(&mut self, library: ShaderLibrary) {
+        *self = Self::new(self.file_name, library, self.interface.clone());
+    }
+
+    /// Drops the permutations built so far, when the pipeline state they were created with
+    /// changes.
+    pub fn clear(&mut self) {
+        self.permutations.clear();
+    }
+
+    /// Builds the permutation for `features` with `create`, unless it's cached. Falls back to
+    /// the builtin modules when the watched ones have errors.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        features: ShaderFeatures,
+        create: impl Fn(&wgpu::ShaderModule, ShaderFeatures) -> P,
+    ) {
+        if self.permutations.contains_key(&features) {
+            return;
+        }
+
+        let pipeline =
+            compile(device, self, &self.sources, features, &create).unwrap_or_else(|error| {
+                log::error!(
+                    "Using the builtin shader, {} has errors:\n{:#}",
+                    self.file_name,
+                    error
+                );
+                compile(device, self, &Sources::default(), features, &create)
+                    .expect("Invalid builtin shader")
+            });
+        self.permutations.insert(features, pipeline);
+    }
+
+    /// Pipeline of a permutation `prepare` built.
+    pub fn get(&self, features: ShaderFeatures) -> &P {
+        &self.permutations[&features]
+    }
+
+    /// Rebuilds the cached permutations when a watched module changed, keeping the previous ones
+    /// when the new modules don't compile.
+    pub fn reload(
+        &mut self,
+        device: &wgpu::Device,
+        create: impl Fn(&wgpu::ShaderModule, ShaderFeatures) -> P,
+    ) {
+        let version = self.library.version();
+        if version == self.version {
+            return;
+        }
+        self.version = version;
+
+        let sources = self.library.snapshot();
+        let rebuilt = self
+            .permutations
+            .keys()
+            .map(|&features| {
+                let pipeline = compile(device, self, &sources, features, &create)?;
+                Ok((features, pipeline))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>();
+
+        match rebuilt {
+            Ok(rebuilt) => {
+                log::info!(
+                    "Rebuilt {} permutation(s) of {}",
+                    rebuilt.len(),
+                    self.file_name
+                );
+                self.permutations = rebuilt;
+                self.sources = sources;
+            }
+            Err(error) => log::error!(
+                "Keeping the previous pipelines, {} has errors:\n{:#}",
+                self.file_name,
+                error
+            ),
+        }
+    }
+}
+
+/// Preprocesses the shader of `pipelines` with the defines of `features` and creates its
+/// pipeline.
+fn compile<P>(
+    device: &wgpu::Device,
+    pipelines: &ShaderPipelines<P>,
+    sources: &Sources,
+    features: ShaderFeatures,
+    create: impl Fn(&wgpu::ShaderModule, ShaderFeatures) -> P,
+) -> anyhow::Result<P> {
+    let defines = features.defines();
+    let source = preprocess(pipelines.file_name, &defines, |name| sources.get(name))?;
+    let label = format!("{} {:?}", pipelines.file_name, defines);
+
+    try_create_pipeline(device, &label, &source, &pipelines.interface, |module| {
+        create(module, features)
+    })
+}
+
+/// Parses and validates WGSL, returning a diagnostic that points at the error when it's invalid.
+pub fn validate(
+    label: &str,
+    source: &str,
+) -> Result<(naga::Module, naga::valid::ModuleInfo), String> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|error| error.emit_to_string_with_path(source, label))?;
+
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .map_err(|error| error.emit_to_string_with_path(source, label))?;
+
+    Ok((module, info))
+}
+
+/// Creates a pipeline from `source` with `create`. Invalid shaders, shaders binding resources
+/// other than the ones of `interface` and pipelines the device rejects give an error instead of
+/// a panic, so the caller can keep the previous pipeline.
+pub fn try_create_pipeline<P>(
+    device: &wgpu::Device,
+    label: &str,
+    source: &str,
+    interface: &ShaderInterface,
+    create: impl FnOnce(&wgpu::ShaderModule) -> P,
+) -> anyhow::Result<P> {
+    let (module, info) = validate(label, source).map_err(anyhow::Error::msg)?;
+    interface.check_compatible(&ShaderInterface::reflect(label, &module, &info)?)?;
+
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let pipeline = create(&module);
+
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(error) => Err(anyhow::anyhow!("{}", error)),
+        None => Ok(pipeline),
+    }
+}
@@ -0,0 +1,61 @@
+const PI: f32 = 3.14159265;
+
+// Roughest highlights are kept from collapsing to a point, which point lights would make too
+// bright to filter
+const MIN_ROUGHNESS: f32 = 0.045;
+
+// Material of the point being shaded
+struct Surface {
+    albedo: vec3<f32>,
+    alpha: f32,
+    metallic: f32,
+    // Perceptual roughness
+    roughness: f32,
+    occlusion: f32,
+    emissive: vec3<f32>,
+};
+
+// Trowbridge-Reitz GGX distribution of the microfacet normals
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Smith shadowing and masking of the microfacets, with the Schlick-GGX approximation for
+// analytic lights
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    let g_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let g_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return g_v * g_l;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Cook-Torrance BRDF times the cosine of the light, for light coming from `light_dir`.
+// Dielectrics reflect 4% at normal incidence, metals their albedo, and only dielectrics have a
+// diffuse part
+fn brdf(surface: Surface, normal: vec3<f32>, view_dir: vec3<f32>, light_dir: vec3<f32>) -> vec3<f32> {
+    let n_dot_l = max(dot(normal, light_dir), 0.0);
+    if n_dot_l <= 0.0 {
+        return vec3<f32>(0.0);
+    }
+
+    let roughness = clamp(surface.roughness, MIN_ROUGHNESS, 1.0);
+    let half_dir = normalize(view_dir + light_dir);
+    let n_dot_v = max(dot(normal, view_dir), 1e-4);
+    let n_dot_h = max(dot(normal, half_dir), 0.0);
+
+    let f0 = mix(vec3<f32>(0.04), surface.albedo, surface.metallic);
+    let fresnel = fresnel_schlick(max(dot(half_dir, view_dir), 0.0), f0);
+    let specular = distribution_ggx(n_dot_h, roughness) * geometry_smith(n_dot_v, n_dot_l, roughness) * fresnel
+        / (4.0 * n_dot_v * n_dot_l + 1e-4);
+    let diffuse = (1.0 - fresnel) * (1.0 - surface.metallic) * surface.albedo / PI;
+
+    return (diffuse + specular) * n_dot_l;
+}
@@ -0,0 +1,66 @@
+#include "lights.wgsl"
+#include "clusters.wgsl"
+
+@group(0) @binding(8)
+var<storage, read_write> cluster_lights: array<u32>;
+
+// View space point on the ray through `ndc` at `view_depth` from the camera
+fn view_point(ndc: vec2<f32>, view_depth: f32) -> vec3<f32> {
+    let far = clusters.inv_proj * vec4<f32>(ndc, 1.0, 1.0);
+    let direction = far.xyz / far.w;
+    return direction * (view_depth / -direction.z);
+}
+
+// Lists the lights whose range reaches each froxel, one froxel per invocation
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let grid = clusters.grid;
+    let index = id.x;
+    if index >= grid.x * grid.y * grid.z {
+        return;
+    }
+
+    let tile = vec2<u32>(index % grid.x, (index / grid.x) % grid.y);
+    let slice = index / (grid.x * grid.y);
+
+    // Bounding box of the froxel in view space
+    let ndc_min = vec2<f32>(tile) / vec2<f32>(grid.xy) * 2.0 - 1.0;
+    let ndc_max = vec2<f32>(tile + 1u) / vec2<f32>(grid.xy) * 2.0 - 1.0;
+    let depth_ratio = clusters.far / clusters.near;
+    let near = clusters.near * pow(depth_ratio, f32(slice) / f32(grid.z));
+    let far = clusters.near * pow(depth_ratio, f32(slice + 1u) / f32(grid.z));
+
+    var box_min = vec3<f32>(3.4e38);
+    var box_max = vec3<f32>(-3.4e38);
+    for (var corner = 0u; corner < 8u; corner++) {
+        let ndc = select(ndc_min, ndc_max, vec2<bool>((corner & 1u) != 0u, (corner & 2u) != 0u));
+        let point = view_point(ndc, select(near, far, (corner & 4u) != 0u));
+        box_min = min(box_min, point);
+        box_max = max(box_max, point);
+    }
+
+    // Spot lights are culled by their range, like point lights. Lights past the capacity of the
+    // list are still counted, so the count tells when some were left out
+    let list = index * grid.w;
+    let capacity = grid.w - 1u;
+    var count = 0u;
+    for (var i = 0u; i < lights.count; i++) {
+        let light = lights.items[i];
+
+        var reaches = true;
+        if light.kind != LIGHT_DIRECTIONAL {
+            let center = (clusters.view * vec4<f32>(light.position.xyz, 1.0)).xyz;
+            let offset = center - clamp(center, box_min, box_max);
+            reaches = dot(offset, offset) <= light.attenuation.w * light.attenuation.w;
+        }
+
+        if reaches {
+            if count < capacity {
+                cluster_lights[list + 1u + count] = i;
+            }
+            count++;
+        }
+    }
+
+    cluster_lights[list] = count;
+}
@@ -0,0 +1,25 @@
+// Froxels the view frustum is split into: tiles of the screen, sliced exponentially in depth
+struct Clusters {
+    // World space to view space
+    view: mat4x4<f32>,
+    // Clip space to view space
+    inv_proj: mat4x4<f32>,
+    // Froxels along x, y and depth in xyz, and the u32s each one takes in the light lists in w:
+    // the count of the lights reaching it, followed by the indices of as many as fit
+    grid: vec4<u32>,
+    near: f32,
+    far: f32,
+};
+
+@group(0) @binding(7)
+var<uniform> clusters: Clusters;
+
+// Froxel of the point at `ndc` on the screen and `view_depth` from the camera
+fn cluster_index(ndc: vec2<f32>, view_depth: f32) -> u32 {
+    let grid = vec3<f32>(clusters.grid.xyz);
+    let tile = clamp(floor((ndc * 0.5 + 0.5) * grid.xy), vec2<f32>(0.0), grid.xy - 1.0);
+    let slice = log(view_depth / clusters.near) / log(clusters.far / clusters.near) * grid.z;
+    let z = clamp(floor(slice), 0.0, grid.z - 1.0);
+
+    return (u32(z) * clusters.grid.y + u32(tile.y)) * clusters.grid.x + u32(tile.x);
+}
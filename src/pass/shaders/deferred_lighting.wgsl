@@ -0,0 +1,54 @@
+#include "globals.wgsl"
+#include "lighting.wgsl"
+
+// Written by gbuffer.wgsl
+@group(1) @binding(0)
+var gbuffer_depth: texture_2d<f32>;
+@group(1) @binding(1)
+var gbuffer_albedo: texture_2d<f32>;
+@group(1) @binding(2)
+var gbuffer_normal: texture_2d<f32>;
+@group(1) @binding(3)
+var gbuffer_material: texture_2d<f32>;
+@group(1) @binding(4)
+var gbuffer_ambient: texture_2d<f32>;
+
+// One triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Lights the surface of each pixel, leaving the background where nothing was drawn
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = vec2<i32>(position.xy);
+    let depth = textureLoad(gbuffer_depth, texel, 0).r;
+    if depth >= 1.0 {
+        discard;
+    }
+
+    // The world position is rebuilt from the depth, through the inverse view projection
+    let uv = position.xy / vec2<f32>(textureDimensions(gbuffer_depth));
+    let ndc = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    let world = globals.inv_view_proj * vec4<f32>(ndc, depth, 1.0);
+    let world_position = world.xyz / world.w;
+    let view_depth = (globals.view_proj * vec4<f32>(world_position, 1.0)).w;
+
+    let albedo = textureLoad(gbuffer_albedo, texel, 0);
+    let normal = textureLoad(gbuffer_normal, texel, 0);
+    let material = textureLoad(gbuffer_material, texel, 0);
+    let ambient = textureLoad(gbuffer_ambient, texel, 0);
+
+    // The ambient light already has the occlusion and emission in it
+    var surface: Surface;
+    surface.albedo = albedo.rgb;
+    surface.alpha = albedo.a;
+    surface.metallic = material.r;
+    surface.roughness = material.g;
+    let view_dir = normalize(globals.view_pos.xyz - world_position);
+    let lit = shade_lights(surface, world_position, normalize(normal.xyz), view_dir, view_depth);
+
+    return vec4<f32>(ambient.rgb + lit, albedo.a);
+}
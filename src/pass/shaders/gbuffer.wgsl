@@ -0,0 +1,29 @@
+#include "locals.wgsl"
+#include "vertex.wgsl"
+
+// Surface of the closest fragment, which deferred_lighting.wgsl lights
+struct GBuffer {
+    // Depth of the fragment, which the world position is rebuilt from
+    @location(0) depth: f32,
+    // Base color, with the texture and tint, and alpha
+    @location(1) albedo: vec4<f32>,
+    // World space normal in xyz
+    @location(2) normal: vec4<f32>,
+    // Metalness in r and roughness in g
+    @location(3) material: vec4<f32>,
+    // Ambient and emitted light, which the lights are added to
+    @location(4) ambient: vec4<f32>,
+};
+
+@fragment
+fn fs_main(in: VertexOutput) -> GBuffer {
+    let surface = material_surface(in.tex_coords, in.tint);
+
+    var out: GBuffer;
+    out.depth = in.clip_position.z;
+    out.albedo = vec4<f32>(surface.albedo, surface.alpha);
+    out.normal = vec4<f32>(mapped_normal(in.tex_coords, in.world_normal, in.world_tangent), 0.0);
+    out.material = vec4<f32>(surface.metallic, surface.roughness, 0.0, 1.0);
+    out.ambient = vec4<f32>(ambient_light(surface), 1.0);
+    return out;
+}
@@ -0,0 +1,9 @@
+struct Globals {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    // Back from clip space to world space
+    inv_view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
@@ -0,0 +1,71 @@
+#include "brdf.wgsl"
+#include "lights.wgsl"
+#include "shadows.wgsl"
+
+#ifdef CLUSTERED
+#include "globals.wgsl"
+#include "clusters.wgsl"
+
+@group(0) @binding(8)
+var<storage, read> cluster_lights: array<u32>;
+#endif
+
+// Light intensities are what a white matte surface facing the light reflects, so the BRDF is
+// scaled by PI
+fn shade(light: Light, surface: Surface, world_position: vec3<f32>, normal: vec3<f32>, view_dir: vec3<f32>, visibility: f32) -> vec3<f32> {
+    var light_dir: vec3<f32>;
+    var attenuation = 1.0;
+
+    if light.kind == LIGHT_DIRECTIONAL {
+        light_dir = normalize(-light.direction.xyz);
+    } else {
+        let to_light = light.position.xyz - world_position;
+        let distance = length(to_light);
+        light_dir = to_light / distance;
+        attenuation = 1.0 / (light.attenuation.x + light.attenuation.y * distance + light.attenuation.z * distance * distance);
+
+        if light.kind == LIGHT_SPOT {
+            let theta = dot(light_dir, normalize(-light.direction.xyz));
+            // Equal cutoffs make a hard edge rather than dividing by zero
+            let epsilon = max(light.cos_inner_cutoff - light.cos_outer_cutoff, 1e-4);
+            attenuation *= clamp((theta - light.cos_outer_cutoff) / epsilon, 0.0, 1.0);
+        }
+    }
+
+    let radiance = light.color.rgb * attenuation * visibility;
+    return PI * brdf(surface, normal, view_dir, light_dir) * radiance;
+}
+
+// Sums the shading of every light, or of the lights of the cluster of the point, with their
+// shadows
+fn shade_lights(surface: Surface, world_position: vec3<f32>, normal: vec3<f32>, view_dir: vec3<f32>, view_depth: f32) -> vec3<f32> {
+    var out = vec3<f32>(0.0);
+
+#ifdef CLUSTERED
+    let clip_position = globals.view_proj * vec4<f32>(world_position, 1.0);
+    let cluster = cluster_index(clip_position.xy / clip_position.w, view_depth) * clusters.grid.w;
+    // The count includes the lights that overflowed the list
+    let count = min(cluster_lights[cluster], clusters.grid.w - 1u);
+#else
+    let count = lights.count;
+#endif
+
+    for (var n = 0u; n < count; n++) {
+#ifdef CLUSTERED
+        let i = cluster_lights[cluster + 1u + n];
+#else
+        let i = n;
+#endif
+        var visibility = 1.0;
+        let light = lights.items[i];
+        if i32(i) == shadow.light_index {
+            visibility = directional_shadow(world_position, view_depth);
+        } else if light.shadow_index >= 0 {
+            visibility = point_shadow(u32(light.shadow_index), world_position);
+        }
+
+        out += shade(light, surface, world_position, normal, view_dir, visibility);
+    }
+
+    return out;
+}
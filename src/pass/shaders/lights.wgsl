@@ -0,0 +1,24 @@
+struct Light {
+    position: vec4<f32>,
+    direction: vec4<f32>,
+    color: vec4<f32>,
+    // Constant, linear and quadratic factors in xyz, and the range clusters cull with in w
+    attenuation: vec4<f32>,
+    cos_inner_cutoff: f32,
+    cos_outer_cutoff: f32,
+    kind: u32,
+    // Slot in the point shadow maps, -1 when the light doesn't cast shadows
+    shadow_index: i32,
+};
+
+struct Lights {
+    count: u32,
+    items: array<Light>,
+};
+
+@group(0) @binding(1)
+var<storage, read> lights: Lights;
+
+const LIGHT_DIRECTIONAL: u32 = 0u;
+const LIGHT_POINT: u32 = 1u;
+const LIGHT_SPOT: u32 = 2u;
@@ -0,0 +1,69 @@
+#include "brdf.wgsl"
+
+struct Locals {
+    // Opacity in w
+    base_color: vec4<f32>,
+    emissive: vec4<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> locals: Locals;
+
+// Share of the base color that the ambient light lights
+const AMBIENT_STRENGTH: f32 = 0.1;
+
+// Every texture of the material is sampled with the sampler of the base color texture. The ones
+// the material doesn't have are white.
+@group(1) @binding(1)
+var tex_view: texture_2d<f32>;
+@group(1) @binding(2)
+var tex_sampler: sampler;
+// Roughness in g and metalness in b
+@group(1) @binding(6)
+var metallic_roughness_map: texture_2d<f32>;
+// Ambient occlusion in r
+@group(1) @binding(7)
+var occlusion_map: texture_2d<f32>;
+@group(1) @binding(8)
+var emissive_map: texture_2d<f32>;
+// Tangent space normals
+@group(1) @binding(9)
+var normal_map: texture_2d<f32>;
+
+// Material of a fragment, from the factors of the material and its textures
+fn material_surface(tex_coords: vec2<f32>, tint: vec3<f32>) -> Surface {
+    let base_color = textureSample(tex_view, tex_sampler, tex_coords);
+    let metallic_roughness = textureSample(metallic_roughness_map, tex_sampler, tex_coords);
+    let occlusion = textureSample(occlusion_map, tex_sampler, tex_coords).r;
+    let emissive = textureSample(emissive_map, tex_sampler, tex_coords).rgb;
+
+    var surface: Surface;
+    surface.albedo = locals.base_color.rgb * base_color.rgb * tint;
+    surface.alpha = locals.base_color.a * base_color.a;
+    surface.metallic = locals.metallic * metallic_roughness.b;
+    surface.roughness = locals.roughness * metallic_roughness.g;
+    surface.occlusion = 1.0 + locals.occlusion_strength * (occlusion - 1.0);
+    surface.emissive = locals.emissive.rgb * emissive;
+    return surface;
+}
+
+// Light of the surface that doesn't come from the lights, which are added to it
+fn ambient_light(surface: Surface) -> vec3<f32> {
+    return AMBIENT_STRENGTH * surface.albedo * surface.occlusion + surface.emissive;
+}
+
+// Normal of a fragment, perturbed by the normal map in the tangent space of the surface
+fn mapped_normal(tex_coords: vec2<f32>, world_normal: vec3<f32>, world_tangent: vec4<f32>) -> vec3<f32> {
+    let normal = normalize(world_normal);
+    // Interpolation skews the tangent off the surface
+    let tangent = normalize(world_tangent.xyz - normal * dot(normal, world_tangent.xyz));
+    let bitangent = cross(normal, tangent) * world_tangent.w;
+
+    var mapped = textureSample(normal_map, tex_sampler, tex_coords).xyz * 2.0 - 1.0;
+    mapped = vec3<f32>(mapped.xy * locals.normal_scale, mapped.z);
+    return normalize(mat3x3<f32>(tangent, bitangent, normal) * mapped);
+}
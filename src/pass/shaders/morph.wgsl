@@ -0,0 +1,30 @@
+#ifdef MORPH_TARGETS
+struct MorphWeights {
+    target_count: u32,
+    vertex_count: u32,
+    weights: array<f32>,
+};
+
+// Position, normal and tangent deltas of each vertex, one morph target after the other
+@group(1) @binding(4)
+var<storage, read> morph_deltas: array<vec4<f32>>;
+
+@group(1) @binding(5)
+var<storage, read> morph_weights: MorphWeights;
+#endif
+
+// Adds the weighted deltas of the morph targets to an attribute of the vertex, whose deltas are
+// at `slot`: 0 for the position, 1 for the normal and 2 for the tangent
+fn morph(vertex_index: u32, slot: u32, value: vec3<f32>) -> vec3<f32> {
+    var morphed = value;
+#ifdef MORPH_TARGETS
+    for (var i = 0u; i < morph_weights.target_count; i += 1u) {
+        let weight = morph_weights.weights[i];
+        if weight != 0.0 {
+            let delta = (i * morph_weights.vertex_count + vertex_index) * 3u + slot;
+            morphed += morph_deltas[delta].xyz * weight;
+        }
+    }
+#endif
+    return morphed;
+}
@@ -0,0 +1,56 @@
+#include "skinning.wgsl"
+#include "morph.wgsl"
+
+struct Face {
+    view_proj: mat4x4<f32>,
+    // Light position in xyz and range in w
+    light_position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> face: Face;
+
+struct VertexInput {
+    @builtin(vertex_index) vertex_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(4) joints: vec4<u32>,
+    @location(5) weights: vec4<f32>,
+};
+
+// Only the model matrix of the instance is needed here
+struct InstanceInput {
+    @location(6) m_matrix_0: vec4<f32>,
+    @location(7) m_matrix_1: vec4<f32>,
+    @location(8) m_matrix_2: vec4<f32>,
+    @location(9) m_matrix_3: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // Morph targets displace the bind pose, before skinning
+    let position = morph(in.vertex_index, 0u, in.position);
+    let skin = skin_matrix(in.joints, in.weights);
+    let m_matrix = mat4x4<f32>(
+        instance.m_matrix_0,
+        instance.m_matrix_1,
+        instance.m_matrix_2,
+        instance.m_matrix_3,
+    );
+    let world_position = m_matrix * skin * vec4<f32>(position, 1.0);
+    out.clip_position = face.view_proj * world_position;
+    out.world_position = world_position.xyz;
+    return out;
+}
+
+// Stores the distance to the light so every face of the cube compares the same way
+@fragment
+fn fs_main(in: VertexOutput) -> @builtin(frag_depth) f32 {
+    return length(in.world_position - face.light_position.xyz) / face.light_position.w;
+}
@@ -0,0 +1,14 @@
+#include "locals.wgsl"
+#include "lighting.wgsl"
+#include "vertex.wgsl"
+
+@fragment
+fn fs_main(in : VertexOutput) -> @location(0) vec4f {
+    let surface = material_surface(in.tex_coords, in.tint);
+
+    let normal = mapped_normal(in.tex_coords, in.world_normal, in.world_tangent);
+    let view_dir = normalize(globals.view_pos.xyz - in.world_position);
+
+    let color = ambient_light(surface) + shade_lights(surface, in.world_position, normal, view_dir, in.view_depth);
+    return vec4<f32>(color, surface.alpha);
+}
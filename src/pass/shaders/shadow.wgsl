@@ -0,0 +1,38 @@
+#include "skinning.wgsl"
+#include "morph.wgsl"
+
+struct Light {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> light: Light;
+
+struct VertexInput {
+    @builtin(vertex_index) vertex_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(4) joints: vec4<u32>,
+    @location(5) weights: vec4<f32>,
+};
+
+// Only the model matrix of the instance is needed here
+struct InstanceInput {
+    @location(6) m_matrix_0: vec4<f32>,
+    @location(7) m_matrix_1: vec4<f32>,
+    @location(8) m_matrix_2: vec4<f32>,
+    @location(9) m_matrix_3: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput, instance: InstanceInput) -> @builtin(position) vec4<f32> {
+    // Morph targets displace the bind pose, before skinning
+    let position = morph(in.vertex_index, 0u, in.position);
+    let skin = skin_matrix(in.joints, in.weights);
+    let m_matrix = mat4x4<f32>(
+        instance.m_matrix_0,
+        instance.m_matrix_1,
+        instance.m_matrix_2,
+        instance.m_matrix_3,
+    );
+    return light.view_proj * m_matrix * skin * vec4<f32>(position, 1.0);
+}
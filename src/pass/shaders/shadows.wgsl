@@ -0,0 +1,131 @@
+const MAX_CASCADES: u32 = 4u;
+
+struct Shadow {
+    cascade_view_proj: array<mat4x4<f32>, MAX_CASCADES>,
+    // View depth where each cascade ends
+    cascade_splits: vec4<f32>,
+    cascade_count: u32,
+    // Index of the shadow casting light, -1 when no light casts shadows
+    light_index: i32,
+    texel_size: f32,
+    cascade_blend: f32,
+};
+
+@group(0) @binding(2)
+var<uniform> shadow: Shadow;
+@group(0) @binding(3)
+var shadow_map: texture_depth_2d_array;
+@group(0) @binding(4)
+var shadow_sampler: sampler_comparison;
+
+const MAX_POINT_SHADOWS: u32 = 4u;
+
+struct PointShadow {
+    // Cube faces in +X, -X, +Y, -Y, +Z, -Z order
+    face_view_proj: array<mat4x4<f32>, 6>,
+    // Light position in xyz and range in w
+    position: vec4<f32>,
+    texel_size: f32,
+    bias: f32,
+};
+
+struct PointShadows {
+    items: array<PointShadow, MAX_POINT_SHADOWS>,
+};
+
+@group(0) @binding(5)
+var<uniform> point_shadows: PointShadows;
+@group(0) @binding(6)
+var point_shadow_map: texture_depth_2d_array;
+
+// Share of light reaching the fragment in a cascade, filtered over 3x3 texels (PCF)
+fn cascade_visibility(cascade: u32, world_position: vec3<f32>) -> f32 {
+    let light_position = shadow.cascade_view_proj[cascade] * vec4<f32>(world_position, 1.0);
+    let ndc = light_position.xyz / light_position.w;
+    let uv = vec2<f32>(ndc.x * 0.5 + 0.5, -ndc.y * 0.5 + 0.5);
+
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0 {
+        return 1.0;
+    }
+
+    var visibility = 0.0;
+    for (var x = -1; x <= 1; x++) {
+        for (var y = -1; y <= 1; y++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * shadow.texel_size;
+            visibility += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, cascade, ndc.z);
+        }
+    }
+
+    return visibility / 9.0;
+}
+
+// Picks the cascade by view depth and fades into the next one near the cascade end
+fn directional_shadow(world_position: vec3<f32>, view_depth: f32) -> f32 {
+    var cascade = shadow.cascade_count;
+    for (var i = 0u; i < shadow.cascade_count; i++) {
+        if view_depth < shadow.cascade_splits[i] {
+            cascade = i;
+            break;
+        }
+    }
+
+    if cascade == shadow.cascade_count {
+        return 1.0;
+    }
+
+    var visibility = cascade_visibility(cascade, world_position);
+
+    if cascade + 1u < shadow.cascade_count {
+        let far = shadow.cascade_splits[cascade];
+        var near = 0.0;
+        if cascade > 0u {
+            near = shadow.cascade_splits[cascade - 1u];
+        }
+
+        let blend_start = far - (far - near) * shadow.cascade_blend;
+        if view_depth > blend_start {
+            let t = (view_depth - blend_start) / (far - blend_start);
+            visibility = mix(visibility, cascade_visibility(cascade + 1u, world_position), t);
+        }
+    }
+
+    return visibility;
+}
+
+// Share of a point light reaching the fragment. The face is picked by the major axis of the
+// direction from the light, and the stored light distances are compared with PCF
+fn point_shadow(slot: u32, world_position: vec3<f32>) -> f32 {
+    // Read through the binding, arrays in values can't be indexed dynamically
+    let point = &point_shadows.items[slot];
+    let from_light = world_position - (*point).position.xyz;
+    let distance = length(from_light) / (*point).position.w;
+
+    if distance > 1.0 {
+        return 1.0;
+    }
+
+    let axis = abs(from_light);
+    var face: u32;
+    if axis.x >= axis.y && axis.x >= axis.z {
+        face = select(1u, 0u, from_light.x > 0.0);
+    } else if axis.y >= axis.z {
+        face = select(3u, 2u, from_light.y > 0.0);
+    } else {
+        face = select(5u, 4u, from_light.z > 0.0);
+    }
+
+    let light_position = (*point).face_view_proj[face] * vec4<f32>(world_position, 1.0);
+    let ndc = light_position.xy / light_position.w;
+    let uv = vec2<f32>(ndc.x * 0.5 + 0.5, -ndc.y * 0.5 + 0.5);
+    let layer = slot * 6u + face;
+
+    var visibility = 0.0;
+    for (var x = -1; x <= 1; x++) {
+        for (var y = -1; y <= 1; y++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * (*point).texel_size;
+            visibility += textureSampleCompareLevel(point_shadow_map, shadow_sampler, uv + offset, layer, distance - (*point).bias);
+        }
+    }
+
+    return visibility / 9.0;
+}
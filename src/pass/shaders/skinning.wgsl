@@ -0,0 +1,24 @@
+#ifdef SKINNED
+// Joint matrices of the skin of the entity
+@group(1) @binding(3)
+var<storage, read> joints: array<mat4x4<f32>>;
+#endif
+
+// Blends the joint matrices influencing the vertex, or leaves it as is when it isn't skinned
+fn skin_matrix(joint_indices: vec4<u32>, weights: vec4<f32>) -> mat4x4<f32> {
+#ifdef SKINNED
+    if dot(weights, vec4<f32>(1.0)) > 0.0 {
+        return joints[joint_indices.x] * weights.x
+            + joints[joint_indices.y] * weights.y
+            + joints[joint_indices.z] * weights.z
+            + joints[joint_indices.w] * weights.w;
+    }
+#endif
+
+    return mat4x4<f32>(
+        vec4<f32>(1.0, 0.0, 0.0, 0.0),
+        vec4<f32>(0.0, 1.0, 0.0, 0.0),
+        vec4<f32>(0.0, 0.0, 1.0, 0.0),
+        vec4<f32>(0.0, 0.0, 0.0, 1.0),
+    );
+}
@@ -0,0 +1,75 @@
+#include "globals.wgsl"
+#include "skinning.wgsl"
+#include "morph.wgsl"
+
+struct VertexInput {
+    @builtin(vertex_index) vertex_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    // Handedness of the bitangent in w
+    @location(3) tangent: vec4<f32>,
+    @location(4) joints: vec4<u32>,
+    @location(5) weights: vec4<f32>,
+};
+
+// Placement and tint of the instance being drawn
+struct InstanceInput {
+    @location(6) m_matrix_0: vec4<f32>,
+    @location(7) m_matrix_1: vec4<f32>,
+    @location(8) m_matrix_2: vec4<f32>,
+    @location(9) m_matrix_3: vec4<f32>,
+    @location(10) normal_matrix_0: vec4<f32>,
+    @location(11) normal_matrix_1: vec4<f32>,
+    @location(12) normal_matrix_2: vec4<f32>,
+    @location(13) tint: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_position: vec3<f32>,
+    @location(2) world_normal: vec3<f32>,
+    @location(3) view_depth: f32,
+    @location(4) tint: vec3<f32>,
+    @location(5) world_tangent: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // Morph targets displace the bind pose, before skinning
+    let position = morph(in.vertex_index, 0u, in.position);
+    let normal = normalize(morph(in.vertex_index, 1u, in.normal));
+    let tangent = morph(in.vertex_index, 2u, in.tangent.xyz);
+    let skin = skin_matrix(in.joints, in.weights);
+    let skin_normal = mat3x3<f32>(skin[0].xyz, skin[1].xyz, skin[2].xyz);
+
+    let m_matrix = mat4x4<f32>(
+        instance.m_matrix_0,
+        instance.m_matrix_1,
+        instance.m_matrix_2,
+        instance.m_matrix_3,
+    );
+    let normal_matrix = mat3x3<f32>(
+        instance.normal_matrix_0.xyz,
+        instance.normal_matrix_1.xyz,
+        instance.normal_matrix_2.xyz,
+    );
+
+    let world_position = m_matrix * skin * vec4<f32>(position, 1.0);
+    out.clip_position = globals.view_proj * world_position;
+    out.view_depth = out.clip_position.w;
+    out.world_position = world_position.xyz;
+    out.world_normal = normal_matrix * (skin_normal * normal);
+
+    // Tangents are directions on the surface, which the model matrix moves like positions.
+    // Mirroring instances flip the bitangent
+    let linear = mat3x3<f32>(m_matrix[0].xyz, m_matrix[1].xyz, m_matrix[2].xyz);
+    let handedness = in.tangent.w * select(1.0, -1.0, determinant(linear) < 0.0);
+    out.world_tangent = vec4<f32>(linear * (skin_normal * tangent), handedness);
+    out.tex_coords = in.tex_coords;
+    out.tint = instance.tint.rgb;
+    return out;
+}
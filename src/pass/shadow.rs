@@ -0,0 +1,417 @@
+use std::mem::size_of;
+
+use anyhow::{ensure, Context};
+use cgmath::{
+    EuclideanSpace, InnerSpace, Matrix, Matrix4, MetricSpace, Point3, SquareMatrix, Vector3,
+};
+
+use super::{
+    point_shadow::MAX_POINT_SHADOWS,
+    reflection::ShaderInterface,
+    shader::{builtin_interface, ShaderFeatures, ShaderLibrary, ShaderPipelines},
+};
+use crate::{
+    camera::{Camera, OPENGL_TO_WGPU_MATRIX},
+    components::{InstanceRaw, Vertex},
+    texture::Texture,
+};
+
+/// Most cascades the lighting shader can sample.
+pub const MAX_CASCADES: usize = 4;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ShadowSettings {
+    /// Width and height of each cascade in texels.
+    pub resolution: u32,
+    /// Constant depth bias, in units of the smallest depth difference.
+    pub depth_bias: i32,
+    /// Depth bias scaled by the slope of the rendered triangle.
+    pub slope_bias: f32,
+    /// How far from the camera shadows are rendered. The cascades split the camera frustum up to
+    /// this distance.
+    pub distance: f32,
+    /// Number of cascades, from 1 to `MAX_CASCADES`.
+    pub cascades: u32,
+    /// Blend between uniform (0.0) and logarithmic (1.0) cascade splits.
+    pub split_lambda: f32,
+    /// Share of each cascade, at its far end, that fades into the next one.
+    pub cascade_blend: f32,
+    /// Width and height of each cube face of point light shadow maps.
+    pub point_resolution: u32,
+    /// Distance from a point light past which it doesn't cast shadows.
+    pub point_range: f32,
+    /// Bias subtracted from the light distance of fragments, as a share of `point_range`.
+    pub point_bias: f32,
+    /// Number of point lights that can cast shadows at once, up to `MAX_POINT_SHADOWS`.
+    pub max_point_shadows: u32,
+}
+
+impl ShadowSettings {
+    fn cascade_count(&self) -> usize {
+        (self.cascades as usize).clamp(1, MAX_CASCADES)
+    }
+
+    pub(super) fn point_shadow_count(&self) -> usize {
+        (self.max_point_shadows as usize).clamp(1, MAX_POINT_SHADOWS)
+    }
+
+    /// Checks that the shadow maps fit in the textures of a device with `limits`, and that the
+    /// cascades cover some distance.
+    pub(super) fn check(&self, limits: &wgpu::Limits) -> anyhow::Result<()> {
+        check_resolution("resolution", self.resolution, limits)?;
+        check_resolution("point_resolution", self.point_resolution, limits)?;
+        ensure!(
+            self.distance.is_finite() && self.distance > 0.0,
+            "Shadow distance {} isn't a positive distance",
+            self.distance
+        );
+
+        Ok(())
+    }
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 2048,
+            depth_bias: 2,
+            slope_bias: 2.0,
+            distance: 100.0,
+            cascades: 4,
+            split_lambda: 0.75,
+            cascade_blend: 0.1,
+            point_resolution: 512,
+            point_range: 25.0,
+            point_bias: 0.002,
+            max_point_shadows: 4,
+        }
+    }
+}
+
+fn check_resolution(name: &str, resolution: u32, limits: &wgpu::Limits) -> anyhow::Result<()> {
+    let max_resolution = limits.max_texture_dimension_2d;
+    ensure!(
+        (1..=max_resolution).contains(&resolution),
+        "Shadow {} {} isn't between 1 and the {} texels the device allows",
+        name,
+        resolution,
+        max_resolution
+    );
+
+    Ok(())
+}
+
+/// Data the lighting shader needs to sample the shadow map.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowUniform {
+    cascade_view_proj: [[[f32; 4]; 4]; MAX_CASCADES],
+    /// View depth where each cascade ends.
+    cascade_splits: [f32; MAX_CASCADES],
+    cascade_count: u32,
+    /// Index of the shadow casting light in the lights buffer, or -1 when there is none.
+    light_index: i32,
+    texel_size: f32,
+    cascade_blend: f32,
+}
+
+impl ShadowUniform {
+    pub fn new(
+        cascades: &[Cascade],
+        light_index: Option<usize>,
+        settings: &ShadowSettings,
+    ) -> Self {
+        let mut uniform = Self {
+            cascade_view_proj: [Matrix4::identity().into(); MAX_CASCADES],
+            cascade_splits: [0.0; MAX_CASCADES],
+            cascade_count: cascades.len() as u32,
+            light_index: light_index.map_or(-1, |index| index as i32),
+            texel_size: 1.0 / settings.resolution as f32,
+            cascade_blend: settings.cascade_blend,
+        };
+
+        for (i, cascade) in cascades.iter().enumerate() {
+            uniform.cascade_view_proj[i] = cascade.view_proj.into();
+            uniform.cascade_splits[i] = cascade.far;
+        }
+
+        uniform
+    }
+}
+
+/// Slice of the camera frustum covered by one shadow map layer.
+#[derive(Clone, Copy, Debug)]
+pub struct Cascade {
+    pub far: f32,
+    pub view_proj: Matrix4<f32>,
+}
+
+/// Depth only pipeline and layered target that render the scene from a directional light, one
+/// layer per cascade.
+pub struct ShadowMap {
+    pub settings: ShadowSettings,
+    pub texture: Texture,
+
+    layer_views: Vec<wgpu::TextureView>,
+    cascade_uniforms: Vec<(wgpu::Buffer, wgpu::BindGroup)>,
+
+    pipeline_layout: wgpu::PipelineLayout,
+    pub(super) pipelines: ShaderPipelines,
+}
+
+impl ShadowMap {
+    pub fn new(
+        device: &wgpu::Device,
+        local_bind_group_layout: &wgpu::BindGroupLayout,
+        local_interface: &ShaderInterface,
+        settings: ShadowSettings,
+        shader_library: ShaderLibrary,
+    ) -> anyhow::Result<Self> {
+        let light_size = size_of::<[[f32; 4]; 4]>() as wgpu::BufferAddress;
+        let cascade_count = settings.cascade_count();
+
+        let interface =
+            reflect_shader(local_interface).context("The shadow shader doesn't match the pass")?;
+        let light_bind_group_layout = interface.bind_group_layout(device, 0, "[Shadow] Light");
+
+        let cascade_uniforms = (0..cascade_count)
+            .map(|_| {
+                let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("[Shadow] Light"),
+                    size: light_size,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("[Shadow] Light"),
+                    layout: &light_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                });
+
+                (buffer, bind_group)
+            })
+            .collect();
+
+        // Some backends treat single layer textures as plain 2D ones, which can't be sampled as
+        // arrays, so at least two layers are allocated
+        let texture = Texture::create_shadow_map(
+            device,
+            settings.resolution,
+            cascade_count.max(2) as u32,
+            "shadow_map",
+        );
+        let layer_views = (0..cascade_count as u32)
+            .map(|layer| texture.create_layer_view(layer))
+            .collect();
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow pipeline layout"),
+            bind_group_layouts: &[&light_bind_group_layout, local_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Ok(Self {
+            settings,
+            texture,
+            layer_views,
+            cascade_uniforms,
+            pipeline_layout,
+            pipelines: ShaderPipelines::new("shadow.wgsl", shader_library, interface),
+        })
+    }
+
+    /// Builds the pipeline for meshes with `features`, unless it's built already.
+    pub fn prepare(&mut self, device: &wgpu::Device, features: ShaderFeatures) {
+        let (layout, settings) = (&self.pipeline_layout, &self.settings);
+        self.pipelines.prepare(device, features, |module, _| {
+            create_pipeline(device, layout, module, settings)
+        });
+    }
+
+    /// Rebuilds the pipelines when their watched shader changed.
+    pub fn reload_shader(&mut self, device: &wgpu::Device) {
+        let (layout, settings) = (&self.pipeline_layout, &self.settings);
+        self.pipelines.reload(device, |module, _| {
+            create_pipeline(device, layout, module, settings)
+        });
+    }
+
+    /// Splits the camera frustum and fits a light projection to each slice. Fails when the
+    /// shadow distance ends before the near plane of `camera`, or its frustum is degenerate.
+    pub fn cascades(
+        &self,
+        direction: Vector3<f32>,
+        camera: &Camera,
+    ) -> anyhow::Result<Vec<Cascade>> {
+        let near = camera.znear();
+        let far = self.settings.distance.min(camera.zfar());
+        ensure!(
+            far > near,
+            "Shadow distance {} doesn't reach past the near plane of the camera at {}",
+            far,
+            near
+        );
+
+        cascade_splits(
+            near,
+            far,
+            self.settings.cascade_count(),
+            self.settings.split_lambda,
+        )
+        .into_iter()
+        .map(|(near, far)| {
+            let view_proj =
+                directional_light_view_proj(direction, camera, near, far, self.settings.resolution)
+                    .context("The view projection of the camera is singular")?;
+            Ok(Cascade { far, view_proj })
+        })
+        .collect()
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, cascades: &[Cascade]) {
+        for ((buffer, _), cascade) in self.cascade_uniforms.iter().zip(cascades) {
+            let view_proj: [[f32; 4]; 4] = cascade.view_proj.into();
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&[view_proj]));
+        }
+    }
+
+    /// Starts the depth pass of a cascade with the light bound. The caller sets the pipeline of
+    /// each mesh from `pipelines`, binds the locals in group 1 and issues the draws.
+    pub fn begin<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        cascade: usize,
+    ) -> wgpu::RenderPass<'a> {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shadow pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.layer_views[cascade],
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_bind_group(0, &self.cascade_uniforms[cascade].1, &[]);
+
+        render_pass
+    }
+}
+
+/// Orthographic view projection for a directional light, covering a bounding sphere of the
+/// camera frustum between `znear` and `zfar`. The sphere keeps the projection size stable while
+/// the camera rotates, and its center is snapped to whole texels so shadow edges don't shimmer.
+/// `None` when the camera frustum is degenerate.
+pub fn directional_light_view_proj(
+    direction: Vector3<f32>,
+    camera: &Camera,
+    znear: f32,
+    zfar: f32,
+    resolution: u32,
+) -> Option<Matrix4<f32>> {
+    let corners = camera.frustum_corners(znear, zfar)?;
+    let center = Point3::centroid(&corners);
+    let radius = corners
+        .iter()
+        .map(|corner| corner.distance(center))
+        .fold(0.0, f32::max)
+        .max(f32::EPSILON);
+
+    let direction = direction.normalize();
+    let up = if direction.y.abs() > 0.99 {
+        Vector3::unit_z()
+    } else {
+        Vector3::unit_y()
+    };
+
+    // Snap the center to the texel grid of the light view
+    let texel_size = 2.0 * radius / resolution as f32;
+    let light_rotation = Matrix4::look_to_rh(Point3::origin(), direction, up);
+    let mut center_in_light = (light_rotation * center.to_homogeneous()).truncate();
+    center_in_light.x = (center_in_light.x / texel_size).floor() * texel_size;
+    center_in_light.y = (center_in_light.y / texel_size).floor() * texel_size;
+    let center =
+        Point3::from_vec((light_rotation.transpose() * center_in_light.extend(1.0)).truncate());
+
+    // Pull the eye back so casters in front of the sphere still land in the depth range
+    let eye = center - direction * radius * 2.0;
+    let view = Matrix4::look_to_rh(eye, direction, up);
+    let projection = cgmath::ortho(-radius, radius, -radius, radius, 0.0, radius * 3.0);
+
+    Some(OPENGL_TO_WGPU_MATRIX * projection * view)
+}
+
+/// Practical split scheme: blends logarithmic and uniform distributions of the cascade ends.
+fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<(f32, f32)> {
+    let mut splits = Vec::with_capacity(count);
+    let mut previous = near;
+
+    for i in 1..=count {
+        let ratio = i as f32 / count as f32;
+        let logarithmic = near * (far / near).powf(ratio);
+        let uniform = near + (far - near) * ratio;
+        let split = lambda * logarithmic + (1.0 - lambda) * uniform;
+
+        splits.push((previous, split));
+        previous = split;
+    }
+
+    splits
+}
+
+/// Resources the shadow shader binds: the light of the cascade, and the locals of the pass in
+/// `local_interface`.
+fn reflect_shader(local_interface: &ShaderInterface) -> anyhow::Result<ShaderInterface> {
+    let interface = builtin_interface("shadow.wgsl")?.group(0);
+    interface.check_size::<[[f32; 4]; 4]>("light")?;
+
+    interface.merge(local_interface.group(1))
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    settings: &ShadowSettings,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Shadow pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc(), InstanceRaw::desc()],
+        },
+        fragment: None,
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: settings.depth_bias,
+                slope_scale: settings.slope_bias,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
@@ -0,0 +1,690 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::Context;
+use base64::Engine;
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, Quaternion, SquareMatrix, Vector3, Vector4};
+use gltf::animation::util::ReadOutputs;
+use wgpu::util::DeviceExt;
+
+use super::{compute_normals, compute_tangents, create_mesh, load_binary, white_texture, Tangents};
+use crate::{
+    animation::{AnimationClip, Channel, Interpolation, Keyframes},
+    assets::{resolve_asset, Assets, Handle},
+    components::{
+        Joint, Material, Mesh, Model, MorphTargets, NodeTransform, SkeletonNode, Skin, Vertex,
+    },
+    texture::Texture,
+};
+
+/// Loads the default scene of a `.gltf` or `.glb` file. Node transforms are baked into the
+/// vertices, and every primitive becomes a mesh with its own material. Skinned meshes stay in
+/// their bind pose instead, and the skins of the file are merged into the skin of the model.
+pub fn load_gltf(
+    file_name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    assets: &Assets,
+) -> anyhow::Result<Model> {
+    let (document, buffers) = load_document(file_name)?;
+    let directory = Path::new(file_name).parent().unwrap_or(Path::new(""));
+
+    let mut loader = Loader {
+        file_name,
+        directory,
+        buffers,
+        device,
+        queue,
+        assets,
+        materials: Vec::new(),
+        default_material: None,
+        meshes: Vec::new(),
+        joint_offsets: Vec::new(),
+    };
+
+    let skin = loader.load_skin(&document);
+    let animations = loader.load_animations(&document);
+
+    for material in document.materials() {
+        let material = loader.load_material(&material)?;
+        loader.materials.push(material);
+    }
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .with_context(|| format!("glTF file {:?} has no scenes", file_name))?;
+
+    for node in scene.nodes() {
+        loader.load_node(&node, Matrix4::identity())?;
+    }
+
+    Ok(Model {
+        meshes: loader.meshes,
+        materials: loader.materials,
+        skin,
+        animations,
+    })
+}
+
+struct Loader<'a> {
+    file_name: &'a str,
+    directory: &'a Path,
+    buffers: Vec<Vec<u8>>,
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+    assets: &'a Assets,
+    materials: Vec<Material>,
+    /// Index of the material used by primitives that don't have one, added when first needed.
+    default_material: Option<usize>,
+    meshes: Vec<(Mesh, usize)>,
+    /// Where the joints of each glTF skin start in the joints of the model skin.
+    joint_offsets: Vec<usize>,
+}
+
+impl Loader<'_> {
+    fn load_node(&mut self, node: &gltf::Node, parent: Matrix4<f32>) -> anyhow::Result<()> {
+        let transform = parent * Matrix4::from(node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            let name = mesh.name().unwrap_or(self.file_name);
+
+            for primitive in mesh.primitives() {
+                if primitive.mode() != gltf::mesh::Mode::Triangles {
+                    log::warn!(
+                        "Skipping primitive of {:?} in {:?} with unsupported mode {:?}",
+                        name,
+                        self.file_name,
+                        primitive.mode()
+                    );
+                    continue;
+                }
+
+                // Skinned vertices are in bind pose, the transform of their node doesn't apply
+                let mesh = match node.skin() {
+                    Some(skin) => self.load_primitive(
+                        &primitive,
+                        node,
+                        name,
+                        Matrix4::identity(),
+                        Some(self.joint_offsets[skin.index()]),
+                    )?,
+                    None => self.load_primitive(&primitive, node, name, transform, None)?,
+                };
+                let material = match primitive.material().index() {
+                    Some(index) => index,
+                    None => self.default_material(),
+                };
+
+                self.meshes.push((mesh, material));
+            }
+        }
+
+        for child in node.children() {
+            self.load_node(&child, transform)?;
+        }
+
+        Ok(())
+    }
+
+    fn load_primitive(
+        &self,
+        primitive: &gltf::Primitive,
+        node: &gltf::Node,
+        name: &str,
+        transform: Matrix4<f32>,
+        joint_offset: Option<usize>,
+    ) -> anyhow::Result<Mesh> {
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+
+        let positions = reader
+            .read_positions()
+            .with_context(|| format!("Primitive of {:?} has no positions", name))?
+            .flatten()
+            .collect::<Vec<_>>();
+        let vertex_count = positions.len() / 3;
+
+        let indices = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect::<Vec<_>>(),
+            None => (0..vertex_count as u32).collect(),
+        };
+        let uvs = match reader.read_tex_coords(0) {
+            Some(uvs) => uvs.into_f32().flatten().collect::<Vec<_>>(),
+            None => vec![0.0; vertex_count * 2],
+        };
+        let normals = match reader.read_normals() {
+            Some(normals) => normals.flatten().collect::<Vec<_>>(),
+            None => compute_normals(&positions, &indices),
+        };
+        // Generated tangents may split vertices, which then copy the attributes of their source
+        let Tangents {
+            tangents,
+            sources,
+            mut indices,
+        } = match reader.read_tangents() {
+            Some(tangents) => Tangents {
+                tangents: tangents.collect(),
+                sources: (0..vertex_count).collect(),
+                indices,
+            },
+            None => compute_tangents(&positions, &normals, &uvs, &indices),
+        };
+        let joints = match (joint_offset, reader.read_joints(0)) {
+            // The joints of every skin are merged, so indices past the first skin grow
+            (Some(offset), Some(joints)) => joints
+                .into_u16()
+                .map(|joints| {
+                    let [a, b, c, d] = joints.map(|joint| u16::try_from(joint as usize + offset));
+                    Ok([a?, b?, c?, d?])
+                })
+                .collect::<Result<Vec<_>, std::num::TryFromIntError>>()
+                .with_context(|| {
+                    format!(
+                        "Primitive of {:?} uses more joints than vertices can index",
+                        name
+                    )
+                })?,
+            _ => vec![[0; 4]; vertex_count],
+        };
+        let weights = match (joint_offset, reader.read_weights(0)) {
+            (Some(_), Some(weights)) => weights
+                .into_f32()
+                .map(|weights| {
+                    let total = weights.iter().sum::<f32>();
+                    if total > 0.0 {
+                        weights.map(|weight| weight / total)
+                    } else {
+                        weights
+                    }
+                })
+                .collect::<Vec<_>>(),
+            _ => vec![[0.0; 4]; vertex_count],
+        };
+
+        let linear = Matrix3::from_cols(
+            transform.x.truncate(),
+            transform.y.truncate(),
+            transform.z.truncate(),
+        );
+        let normal_matrix = linear
+            .invert()
+            .map_or(Matrix3::identity(), |inverse| inverse.transpose());
+
+        // Mirroring transforms flip the winding of the triangles and the bitangents
+        let mirrored = linear.determinant() < 0.0;
+        if mirrored {
+            for triangle in indices.chunks_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+
+        let vertices = sources
+            .iter()
+            .zip(tangents)
+            .map(|(&i, tangent)| {
+                let position =
+                    Vector3::new(positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]);
+                let normal = Vector3::new(normals[i * 3], normals[i * 3 + 1], normals[i * 3 + 2]);
+                let tangent = Vector4::from(tangent);
+
+                let position = transform * position.extend(1.0);
+                let normal = (normal_matrix * normal).normalize();
+                let tangent_direction = (linear * tangent.truncate()).normalize();
+                let handedness = if mirrored { -tangent.w } else { tangent.w };
+
+                Vertex {
+                    position: position.truncate().into(),
+                    uv: [uvs[i * 2], uvs[i * 2 + 1]],
+                    normal: normal.into(),
+                    tangent: tangent_direction.extend(handedness).into(),
+                    joints: joints[i],
+                    weights: weights[i],
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut mesh = create_mesh(name, &vertices, &indices, self.device, self.queue);
+
+        let targets = reader.read_morph_targets().collect::<Vec<_>>();
+        if !targets.is_empty() {
+            // Deltas are directions, so only the linear part of the transform applies
+            let deltas_per_target = sources.len() * MorphTargets::DELTAS_PER_VERTEX;
+            let mut deltas = vec![[0.0; 4]; targets.len() * deltas_per_target];
+            for (target, (positions, normals, tangents)) in targets.into_iter().enumerate() {
+                let target_deltas = &mut deltas[target * deltas_per_target..];
+                let attributes = [
+                    positions.map(|deltas| (deltas.collect::<Vec<_>>(), linear)),
+                    normals.map(|deltas| (deltas.collect::<Vec<_>>(), normal_matrix)),
+                    tangents.map(|deltas| (deltas.collect::<Vec<_>>(), linear)),
+                ];
+
+                for (attribute, values) in attributes.into_iter().enumerate() {
+                    let Some((values, matrix)) = values else {
+                        continue;
+                    };
+                    for (i, &source) in sources.iter().enumerate() {
+                        let Some(&delta) = values.get(source) else {
+                            continue;
+                        };
+                        target_deltas[i * MorphTargets::DELTAS_PER_VERTEX + attribute] =
+                            (matrix * Vector3::from(delta)).extend(0.0).into();
+                    }
+                }
+            }
+
+            mesh.morph_targets = Some(MorphTargets {
+                buffer: self
+                    .device
+                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some(&format!("{:?} Morph targets", name)),
+                        contents: bytemuck::cast_slice(&deltas),
+                        usage: wgpu::BufferUsages::STORAGE,
+                    }),
+                count: deltas.len() / deltas_per_target,
+                vertex_count: sources.len(),
+                node: Some(node.index()),
+                default_weights: node_weights(node),
+            });
+        }
+
+        Ok(mesh)
+    }
+
+    /// Gathers every node of the file into the skeleton, so node indices stay the same, and the
+    /// joints of all the skins one after the other.
+    fn load_skin(&mut self, document: &gltf::Document) -> Option<Skin> {
+        let has_morph_targets = document.meshes().any(|mesh| {
+            mesh.primitives()
+                .any(|primitive| primitive.morph_targets().len() > 0)
+        });
+        if document.skins().len() == 0 && !has_morph_targets {
+            return None;
+        }
+
+        let mut nodes = document
+            .nodes()
+            .map(|node| {
+                let (translation, [x, y, z, w], scale) = node.transform().decomposed();
+
+                SkeletonNode {
+                    name: node.name().map(str::to_string),
+                    parent: None,
+                    rest: NodeTransform {
+                        translation: translation.into(),
+                        rotation: Quaternion::new(w, x, y, z),
+                        scale: scale.into(),
+                    },
+                    weights: node_weights(&node),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for node in document.nodes() {
+            for child in node.children() {
+                nodes[child.index()].parent = Some(node.index());
+            }
+        }
+
+        let mut joints = Vec::new();
+        for skin in document.skins() {
+            self.joint_offsets.push(joints.len());
+
+            let reader = skin.reader(|buffer| Some(&self.buffers[buffer.index()]));
+            let mut inverse_bind_matrices = reader
+                .read_inverse_bind_matrices()
+                .into_iter()
+                .flatten()
+                .map(Matrix4::from);
+
+            joints.extend(skin.joints().map(|node| Joint {
+                node: node.index(),
+                inverse_bind_matrix: inverse_bind_matrices.next().unwrap_or(Matrix4::identity()),
+            }));
+        }
+
+        Some(Skin { nodes, joints })
+    }
+
+    fn load_animations(&self, document: &gltf::Document) -> Vec<AnimationClip> {
+        document
+            .animations()
+            .map(|animation| {
+                let channels = animation
+                    .channels()
+                    .filter_map(|channel| {
+                        let reader = channel.reader(|buffer| Some(&self.buffers[buffer.index()]));
+                        let times = reader.read_inputs()?.collect();
+
+                        let keyframes = match reader.read_outputs()? {
+                            ReadOutputs::Translations(values) => {
+                                Keyframes::Translation(values.map(Vector3::from).collect())
+                            }
+                            ReadOutputs::Rotations(values) => Keyframes::Rotation(
+                                values
+                                    .into_f32()
+                                    .map(|[x, y, z, w]| Quaternion::new(w, x, y, z))
+                                    .collect(),
+                            ),
+                            ReadOutputs::Scales(values) => {
+                                Keyframes::Scale(values.map(Vector3::from).collect())
+                            }
+                            ReadOutputs::MorphTargetWeights(values) => {
+                                Keyframes::Weights(values.into_f32().collect())
+                            }
+                        };
+
+                        let interpolation = match channel.sampler().interpolation() {
+                            gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                            gltf::animation::Interpolation::Step => Interpolation::Step,
+                            gltf::animation::Interpolation::CubicSpline => {
+                                Interpolation::CubicSpline
+                            }
+                        };
+
+                        Some(Channel {
+                            node: channel.target().node().index(),
+                            interpolation,
+                            times,
+                            keyframes,
+                        })
+                    })
+                    .collect();
+
+                AnimationClip {
+                    name: animation.name().unwrap_or_default().to_string(),
+                    channels,
+                }
+            })
+            .collect()
+    }
+
+    /// Textures the material doesn't have are left to the factors. Only blended materials keep
+    /// the alpha of the base color.
+    fn load_material(&self, material: &gltf::Material) -> anyhow::Result<Material> {
+        let name = material.name().unwrap_or("glTF material");
+        let pbr = material.pbr_metallic_roughness();
+
+        let texture = match pbr.base_color_texture() {
+            Some(info) => self.load_texture(&info.texture(), name, Texture::COLOR_FORMAT)?,
+            None => Handle::new(white_texture(self.device, self.queue, name)),
+        };
+        let metallic_roughness_texture = pbr
+            .metallic_roughness_texture()
+            .map(|info| self.load_texture(&info.texture(), name, Texture::LINEAR_FORMAT))
+            .transpose()?;
+        let normal_texture = material
+            .normal_texture()
+            .map(|normal| {
+                let texture = self.load_texture(&normal.texture(), name, Texture::LINEAR_FORMAT)?;
+                anyhow::Ok((texture, normal.scale()))
+            })
+            .transpose()?;
+        let occlusion_texture = material
+            .occlusion_texture()
+            .map(|occlusion| {
+                let texture =
+                    self.load_texture(&occlusion.texture(), name, Texture::LINEAR_FORMAT)?;
+                anyhow::Ok((texture, occlusion.strength()))
+            })
+            .transpose()?;
+        let emissive_texture = material
+            .emissive_texture()
+            .map(|info| self.load_texture(&info.texture(), name, Texture::COLOR_FORMAT))
+            .transpose()?;
+
+        let [r, g, b, alpha] = pbr.base_color_factor();
+
+        Ok(Material {
+            name: name.to_string(),
+            texture,
+            base_color: [r, g, b],
+            opacity: match material.alpha_mode() {
+                gltf::material::AlphaMode::Blend => alpha,
+                _ => Material::DEFAULT_OPACITY,
+            },
+            metallic: pbr.metallic_factor(),
+            roughness: pbr.roughness_factor(),
+            metallic_roughness_texture,
+            normal_scale: normal_texture.as_ref().map_or(1.0, |(_, scale)| *scale),
+            normal_texture: normal_texture.map(|(texture, _)| texture),
+            occlusion_strength: occlusion_texture
+                .as_ref()
+                .map_or(1.0, |(_, strength)| *strength),
+            occlusion_texture: occlusion_texture.map(|(texture, _)| texture),
+            emissive: material.emissive_factor(),
+            emissive_texture,
+        })
+    }
+
+    fn default_material(&mut self) -> usize {
+        *self.default_material.get_or_insert_with(|| {
+            let texture = white_texture(self.device, self.queue, "Default material");
+            self.materials
+                .push(Material::new("Default material", texture));
+            self.materials.len() - 1
+        })
+    }
+
+    /// Texture shared by the materials of the file that use it, and by the other models loaded
+    /// from the same file.
+    ///
+    /// Textures holding data, like normals, are loaded with a linear `format`, apart from the
+    /// color ones.
+    fn load_texture(
+        &self,
+        texture: &gltf::Texture,
+        label: &str,
+        format: wgpu::TextureFormat,
+    ) -> anyhow::Result<Handle<Texture>> {
+        let key = format!("{}#texture{}#{:?}", self.file_name, texture.index(), format);
+        let (file_name, index) = (self.file_name.to_string(), texture.index());
+
+        self.assets.texture_or_load(
+            &key,
+            || {
+                // Embedded images are read from the already loaded buffers, so the files they
+                // come from are resolved again to reload the texture when they change
+                resolve_asset(self.file_name)?;
+                if let gltf::image::Source::View { view, .. } = texture.source().source() {
+                    if let gltf::buffer::Source::Uri(uri) = view.buffer().source() {
+                        if !uri.starts_with("data:") {
+                            let path = self.directory.join(percent_decode(uri));
+                            resolve_asset(&path.to_string_lossy())?;
+                        }
+                    }
+                }
+
+                decode_texture(
+                    texture,
+                    &self.buffers,
+                    self.directory,
+                    self.device,
+                    self.queue,
+                    label,
+                    format,
+                )
+            },
+            Arc::new(move |_, device, queue| {
+                reload_texture(&file_name, index, format, device, queue)
+            }),
+        )
+    }
+}
+
+/// Parses a `.gltf` or `.glb` file and loads its buffers.
+fn load_document(file_name: &str) -> anyhow::Result<(gltf::Gltf, Vec<Vec<u8>>)> {
+    let data = load_binary(file_name)?;
+    let document = gltf::Gltf::from_slice(&data)
+        .with_context(|| format!("Couldn't parse glTF file {:?}", file_name))?;
+    let directory = Path::new(file_name).parent().unwrap_or(Path::new(""));
+
+    let buffers = document
+        .buffers()
+        .map(|buffer| load_buffer(&buffer, document.blob.as_deref(), directory))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok((document, buffers))
+}
+
+/// Loads a texture of a glTF file again, reading the file from scratch.
+fn reload_texture(
+    file_name: &str,
+    index: usize,
+    format: wgpu::TextureFormat,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<Texture> {
+    let (document, buffers) = load_document(file_name)?;
+    let directory = Path::new(file_name).parent().unwrap_or(Path::new(""));
+    let texture = document
+        .textures()
+        .nth(index)
+        .with_context(|| format!("glTF file {:?} has no texture {}", file_name, index))?;
+
+    let label = format!("{}#texture{}", file_name, index);
+    decode_texture(&texture, &buffers, directory, device, queue, &label, format)
+}
+
+fn decode_texture(
+    texture: &gltf::Texture,
+    buffers: &[Vec<u8>],
+    directory: &Path,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    label: &str,
+    format: wgpu::TextureFormat,
+) -> anyhow::Result<Texture> {
+    let bytes = match texture.source().source() {
+        gltf::image::Source::View { view, .. } => buffers
+            .get(view.buffer().index())
+            .and_then(|buffer| buffer.get(view.offset()..view.offset().checked_add(view.length())?))
+            .with_context(|| format!("Texture {:?} lies outside of its buffer", label))?
+            .to_vec(),
+        gltf::image::Source::Uri { uri, .. } => load_uri(uri, directory)?,
+    };
+    let image = image::load_from_memory(&bytes)
+        .with_context(|| format!("Couldn't decode texture of {:?}", label))?;
+
+    let mut texture_data = Texture::from_image_with_format(device, queue, &image, label, format);
+    texture_data.sampler = create_sampler(device, &texture.sampler());
+
+    Ok(texture_data)
+}
+
+/// Initial morph target weights of the mesh of a node, zero when neither the node nor the mesh
+/// sets them.
+fn node_weights(node: &gltf::Node) -> Vec<f32> {
+    let Some(mesh) = node.mesh() else {
+        return Vec::new();
+    };
+    let target_count = mesh
+        .primitives()
+        .map(|primitive| primitive.morph_targets().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut weights = node
+        .weights()
+        .or(mesh.weights())
+        .map_or(Vec::new(), <[f32]>::to_vec);
+    weights.resize(target_count, 0.0);
+    weights
+}
+
+fn load_buffer(
+    buffer: &gltf::Buffer,
+    blob: Option<&[u8]>,
+    directory: &Path,
+) -> anyhow::Result<Vec<u8>> {
+    let data = match buffer.source() {
+        gltf::buffer::Source::Bin => blob
+            .context("glTF buffer refers to a missing binary chunk")?
+            .to_vec(),
+        gltf::buffer::Source::Uri(uri) => load_uri(uri, directory)?,
+    };
+
+    if data.len() < buffer.length() {
+        anyhow::bail!(
+            "glTF buffer {} has {} bytes, expected {}",
+            buffer.index(),
+            data.len(),
+            buffer.length()
+        );
+    }
+
+    Ok(data)
+}
+
+/// Decodes base64 data URIs, and loads other URIs relative to the glTF file.
+fn load_uri(uri: &str, directory: &Path) -> anyhow::Result<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data
+            .split_once(";base64,")
+            .context("Only base64 data URIs are supported")?;
+
+        return Ok(base64::engine::general_purpose::STANDARD.decode(encoded)?);
+    }
+
+    let path = directory.join(percent_decode(uri));
+    let path = path
+        .to_str()
+        .with_context(|| format!("Invalid path in glTF URI {:?}", uri))?;
+
+    load_binary(path)
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn create_sampler(device: &wgpu::Device, sampler: &gltf::texture::Sampler) -> wgpu::Sampler {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+    let address_mode = |mode| match mode {
+        WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+    };
+
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+        _ => wgpu::FilterMode::Linear,
+    };
+    let min_filter = match sampler.min_filter() {
+        Some(
+            MinFilter::Nearest | MinFilter::NearestMipmapNearest | MinFilter::NearestMipmapLinear,
+        ) => wgpu::FilterMode::Nearest,
+        _ => wgpu::FilterMode::Linear,
+    };
+
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter,
+        min_filter,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,510 @@
+mod gltf;
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{BufReader, Cursor},
+    mem::size_of_val,
+    path::Path,
+};
+
+use anyhow::Context;
+use cgmath::{InnerSpace, Vector3};
+
+use crate::{
+    assets::{resolve_asset, Assets, Handle},
+    components::{Material, Mesh, Model, Vertex},
+    texture::Texture,
+};
+
+pub fn load_string(file_name: &str) -> anyhow::Result<String> {
+    let path = resolve_asset(file_name)?;
+    let txt = std::fs::read_to_string(&path)
+        .with_context(|| format!("Couldn't read asset {:?}", path))?;
+
+    Ok(txt)
+}
+
+pub fn load_binary(file_name: &str) -> anyhow::Result<Vec<u8>> {
+    let path = resolve_asset(file_name)?;
+    let data = std::fs::read(&path).with_context(|| format!("Couldn't read asset {:?}", path))?;
+
+    Ok(data)
+}
+
+pub fn load_texture(
+    file_name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<Texture> {
+    let data = load_binary(file_name)?;
+    let image = image::load_from_memory(&data)
+        .with_context(|| format!("Couldn't decode texture {:?}", file_name))?;
+
+    Ok(Texture::from_image(device, queue, &image, file_name))
+}
+
+/// Loads a texture that holds data rather than colors, like roughness or normals.
+pub fn load_linear_texture(
+    file_name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<Texture> {
+    let data = load_binary(file_name)?;
+    let image = image::load_from_memory(&data)
+        .with_context(|| format!("Couldn't decode texture {:?}", file_name))?;
+
+    Ok(Texture::from_linear_image(device, queue, &image, file_name))
+}
+
+/// Loads an OBJ, glTF or GLB model, picked by the file extension. Use `Assets::load_model` to
+/// share models and textures instead.
+pub fn load_model(
+    file_name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<Model> {
+    load_model_with(file_name, device, queue, &Assets::new())
+}
+
+/// Loads a model, getting its textures from `assets`.
+pub(crate) fn load_model_with(
+    file_name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    assets: &Assets,
+) -> anyhow::Result<Model> {
+    match Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        Some("gltf" | "glb") => self::gltf::load_gltf(file_name, device, queue, assets),
+        _ => load_obj(file_name, device, queue, assets),
+    }
+}
+
+fn load_obj(
+    file_name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    assets: &Assets,
+) -> anyhow::Result<Model> {
+    let obj_text = load_string(file_name)?;
+    // Material libraries and textures are relative to the OBJ file
+    let directory = Path::new(file_name).parent().unwrap_or(Path::new(""));
+    let obj_cursor = Cursor::new(obj_text);
+    let mut obj_reader = BufReader::new(obj_cursor);
+
+    // tobj only takes its own error type back, so the real one is kept aside
+    let material_error = RefCell::new(None);
+    let (models, obj_materials) = tobj::load_obj_buf(
+        &mut obj_reader,
+        &tobj::LoadOptions {
+            triangulate: true,
+            single_index: true,
+            ..Default::default()
+        },
+        |p| {
+            let mat_file = directory.join(p);
+            match load_string(&mat_file.to_string_lossy()) {
+                Ok(mat_text) => tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text))),
+                Err(error) => {
+                    *material_error.borrow_mut() = Some(error);
+                    Err(tobj::LoadError::OpenFileFailed)
+                }
+            }
+        },
+    )?;
+    if let Some(error) = material_error.into_inner() {
+        return Err(error.context(format!("Couldn't load the materials of {:?}", file_name)));
+    }
+
+    let mut materials = Vec::new();
+    for m in obj_materials? {
+        // Metallic-roughness has no separate ambient or specular color
+        let ignored = [
+            ("Ka", m.ambient.is_some()),
+            ("Ks", m.specular.is_some()),
+            ("map_Ks", m.specular_texture.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, present)| present.then_some(name))
+        .collect::<Vec<_>>();
+        if !ignored.is_empty() {
+            log::debug!(
+                "Ignoring {} of material {:?} in {:?}",
+                ignored.join(", "),
+                m.name,
+                file_name
+            );
+        }
+
+        let texture = match &m.diffuse_texture {
+            Some(diffuse_texture) => {
+                let path = directory.join(diffuse_texture);
+                assets.load_texture(&path.to_string_lossy(), device, queue)?
+            }
+            None => Handle::new(white_texture(device, queue, &m.name)),
+        };
+        let (normal_texture, normal_scale) = match &m.normal_texture {
+            Some(normal_texture) => {
+                let (file, scale) = parse_bump_map(normal_texture);
+                let path = directory.join(file);
+                let texture = assets.load_linear_texture(&path.to_string_lossy(), device, queue)?;
+                (Some(texture), scale)
+            }
+            None => (None, 1.0),
+        };
+        let emissive_texture = match m.unknown_param.get("map_Ke") {
+            Some(emissive_texture) => {
+                let path = directory.join(emissive_texture);
+                Some(assets.load_texture(&path.to_string_lossy(), device, queue)?)
+            }
+            None => None,
+        };
+
+        // The PBR extension of MTL sets the roughness and metalness, otherwise the roughness
+        // comes from the specular exponent
+        let scalar = |key| {
+            m.unknown_param
+                .get(key)
+                .and_then(|value| value.parse().ok())
+        };
+        let roughness = scalar("Pr").or(m.shininess.map(Material::roughness_from_shininess));
+
+        materials.push(Material {
+            name: m.name,
+            texture,
+            base_color: m.diffuse.unwrap_or(Material::DEFAULT_BASE_COLOR),
+            opacity: m.dissolve.unwrap_or(Material::DEFAULT_OPACITY),
+            metallic: scalar("Pm").unwrap_or(Material::DEFAULT_METALLIC),
+            roughness: roughness.unwrap_or(Material::DEFAULT_ROUGHNESS),
+            metallic_roughness_texture: None,
+            normal_texture,
+            normal_scale,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive: m
+                .unknown_param
+                .get("Ke")
+                .and_then(|value| parse_color(value))
+                .unwrap_or(Material::DEFAULT_EMISSIVE),
+            emissive_texture,
+        })
+    }
+
+    // Index of the material of the meshes that don't have one, added when first needed
+    let mut default_material = None;
+
+    let meshes = models
+        .into_iter()
+        .map(|m| {
+            let normals = if m.mesh.normals.is_empty() {
+                compute_normals(&m.mesh.positions, &m.mesh.indices)
+            } else {
+                m.mesh.normals.clone()
+            };
+            // Meshes without `vt` lines get zero texture coordinates, like glTF ones without any
+            let uvs = if m.mesh.texcoords.is_empty() {
+                vec![0.0; m.mesh.positions.len() / 3 * 2]
+            } else {
+                m.mesh.texcoords.clone()
+            };
+
+            let tangents = compute_tangents(&m.mesh.positions, &normals, &uvs, &m.mesh.indices);
+
+            let vertices = tangents
+                .sources
+                .iter()
+                .zip(&tangents.tangents)
+                .map(|(&i, &tangent)| Vertex {
+                    position: [
+                        m.mesh.positions[i * 3],
+                        m.mesh.positions[i * 3 + 1],
+                        m.mesh.positions[i * 3 + 2],
+                    ],
+                    uv: [uvs[i * 2], uvs[i * 2 + 1]],
+                    normal: [normals[i * 3], normals[i * 3 + 1], normals[i * 3 + 2]],
+                    tangent,
+                    joints: [0; 4],
+                    weights: [0.0; 4],
+                })
+                .collect::<Vec<_>>();
+
+            let material = match m.mesh.material_id {
+                Some(material) => material,
+                None => *default_material.get_or_insert_with(|| {
+                    let texture = white_texture(device, queue, "Default material");
+                    materials.push(Material::new("Default material", texture));
+                    materials.len() - 1
+                }),
+            };
+
+            (
+                create_mesh(&m.name, &vertices, &tangents.indices, device, queue),
+                material,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Model {
+        meshes,
+        materials,
+        skin: None,
+        animations: Vec::new(),
+    })
+}
+
+fn create_mesh(
+    name: &str,
+    vertices: &[Vertex],
+    indices: &[u32],
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> Mesh {
+    let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(&format!("{:?} Vertex Buffer", name)),
+        size: size_of_val(vertices) as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    queue.write_buffer(&vertex_buffer, 0, bytemuck::cast_slice(vertices));
+
+    let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(&format!("{:?} Index Buffer", name)),
+        size: size_of_val(indices) as u64,
+        usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    queue.write_buffer(&index_buffer, 0, bytemuck::cast_slice(indices));
+
+    Mesh {
+        name: name.to_string(),
+        vertex_buffer,
+        index_buffer,
+        index_count: indices.len(),
+        morph_targets: None,
+    }
+}
+
+/// Smooth vertex normals for meshes that don't provide any, averaging the faces around each vertex.
+fn compute_normals(positions: &[f32], indices: &[u32]) -> Vec<f32> {
+    let position = |i: u32| {
+        let i = i as usize * 3;
+        cgmath::Vector3::new(positions[i], positions[i + 1], positions[i + 2])
+    };
+
+    let mut normals = vec![cgmath::Vector3::new(0.0, 0.0, 0.0); positions.len() / 3];
+    for triangle in indices.chunks(3) {
+        let (a, b, c) = (
+            position(triangle[0]),
+            position(triangle[1]),
+            position(triangle[2]),
+        );
+        let face_normal = (b - a).cross(c - a);
+        for &i in triangle {
+            normals[i as usize] += face_normal;
+        }
+    }
+
+    normals
+        .into_iter()
+        .flat_map(|n| {
+            let n = if n.magnitude2() > 0.0 {
+                n.normalize()
+            } else {
+                n
+            };
+            [n.x, n.y, n.z]
+        })
+        .collect()
+}
+
+/// Tangents of a mesh, whose vertices are split where the faces sharing them need different
+/// tangents, like across mirrored texture seams.
+struct Tangents {
+    /// `xyz` and the bitangent handedness in `w` of each vertex.
+    tangents: Vec<[f32; 4]>,
+    /// Vertex of the source mesh each vertex copies.
+    sources: Vec<usize>,
+    /// Triangles of the split vertices.
+    indices: Vec<u32>,
+}
+
+/// Vertex tangents for meshes that don't provide any, generated with MikkTSpace like the tools
+/// that bake normal maps.
+fn compute_tangents(positions: &[f32], normals: &[f32], uvs: &[f32], indices: &[u32]) -> Tangents {
+    let vertex_count = positions.len() / 3;
+    let mut geometry = TangentGeometry {
+        positions,
+        normals,
+        uvs,
+        indices,
+        tangents: vec![[0.0; 4]; indices.len()],
+    };
+    if uvs.len() >= vertex_count * 2 && !bevy_mikktspace::generate_tangents(&mut geometry) {
+        log::warn!("Couldn't generate the tangents of a mesh");
+    }
+
+    let finish = |vertex: usize, [x, y, z, handedness]: [f32; 4]| {
+        let normal = Vector3::new(
+            normals[vertex * 3],
+            normals[vertex * 3 + 1],
+            normals[vertex * 3 + 2],
+        );
+        let mut tangent = Vector3::new(x, y, z);
+
+        // Any direction on the surface will do when the texture coordinates don't give one
+        if tangent.magnitude2() <= f32::EPSILON {
+            let axis = if normal.x.abs() < 0.9 {
+                Vector3::unit_x()
+            } else {
+                Vector3::unit_y()
+            };
+            tangent = (axis - normal * normal.dot(axis)).normalize();
+        }
+        let handedness = if handedness < 0.0 { -1.0 } else { 1.0 };
+
+        [tangent.x, tangent.y, tangent.z, handedness]
+    };
+
+    // Vertices keep the tangent of their first corner, and a copy is added for each other
+    // tangent their corners have
+    let mut tangents = vec![None; vertex_count];
+    let mut sources = (0..vertex_count).collect::<Vec<_>>();
+    let mut copies = HashMap::new();
+    let indices = indices
+        .iter()
+        .zip(geometry.tangents)
+        .map(|(&vertex, tangent)| {
+            let tangent = finish(vertex as usize, tangent);
+            match tangents[vertex as usize] {
+                None => {
+                    tangents[vertex as usize] = Some(tangent);
+                    vertex
+                }
+                Some(first) if first == tangent => vertex,
+                Some(_) => *copies
+                    .entry((vertex, tangent.map(f32::to_bits)))
+                    .or_insert_with(|| {
+                        tangents.push(Some(tangent));
+                        sources.push(vertex as usize);
+                        (tangents.len() - 1) as u32
+                    }),
+            }
+        })
+        .collect();
+
+    Tangents {
+        tangents: tangents
+            .into_iter()
+            .zip(&sources)
+            .map(|(tangent, &source)| tangent.unwrap_or_else(|| finish(source, [0.0; 4])))
+            .collect(),
+        sources,
+        indices,
+    }
+}
+
+/// Triangles of an indexed mesh, as MikkTSpace reads them.
+struct TangentGeometry<'a> {
+    positions: &'a [f32],
+    normals: &'a [f32],
+    uvs: &'a [f32],
+    indices: &'a [u32],
+    /// Tangent of each corner of the triangles, in the order of `indices`.
+    tangents: Vec<[f32; 4]>,
+}
+
+impl TangentGeometry<'_> {
+    fn index(&self, face: usize, vert: usize) -> usize {
+        self.indices[face * 3 + vert] as usize
+    }
+}
+
+impl bevy_mikktspace::Geometry for TangentGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        let i = self.index(face, vert) * 3;
+        [
+            self.positions[i],
+            self.positions[i + 1],
+            self.positions[i + 2],
+        ]
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        let i = self.index(face, vert) * 3;
+        [self.normals[i], self.normals[i + 1], self.normals[i + 2]]
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        let i = self.index(face, vert) * 2;
+        [self.uvs[i], self.uvs[i + 1]]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = tangent;
+    }
+}
+
+/// File and scale of a `map_Bump` or `bump` statement, which are tangent space normal maps. A
+/// `-bm <scale>` option scales the normals like the glTF normal scale.
+fn parse_bump_map(value: &str) -> (&str, f32) {
+    if let Some(options) = value.strip_prefix("-bm") {
+        let options = options.trim_start();
+        if let Some((scale, file)) = options.split_once(char::is_whitespace) {
+            if let Ok(scale) = scale.parse() {
+                return (file.trim(), scale);
+            }
+        }
+    }
+
+    (value, 1.0)
+}
+
+/// Color of an MTL statement like `Ke 1.0 0.5 0.0`.
+fn parse_color(value: &str) -> Option<[f32; 3]> {
+    let mut channels = value.split_whitespace().map(str::parse::<f32>);
+    let color = [
+        channels.next()?.ok()?,
+        channels.next()?.ok()?,
+        channels.next()?.ok()?,
+    ];
+    Some(color)
+}
+
+/// Normal map of a flat surface, for materials without one.
+pub(crate) fn flat_normal_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    label: &str,
+) -> Texture {
+    let image = image::RgbaImage::from_pixel(1, 1, image::Rgba([128, 128, 255, 255]));
+    Texture::from_linear_image(
+        device,
+        queue,
+        &image::DynamicImage::ImageRgba8(image),
+        label,
+    )
+}
+
+/// Plain white texture for materials without one, so the material colors show as they are.
+pub(crate) fn white_texture(device: &wgpu::Device, queue: &wgpu::Queue, label: &str) -> Texture {
+    let image = image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
+    Texture::from_image(
+        device,
+        queue,
+        &image::DynamicImage::ImageRgba8(image),
+        label,
+    )
+}
@@ -20,6 +20,12 @@ pub struct Window {
     pub window: Arc<window::Window>,
 }
 
+impl Default for Window {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Window {
     pub fn new() -> Self {
         let event_loop = EventLoop::new().unwrap();
@@ -0,0 +1,190 @@
+use cgmath::{Deg, InnerSpace, Quaternion, Rotation3, Vector3};
+use wgpu_renderer::{
+    AnimationClip, AnimationPlayer, Channel, Interpolation, Keyframes, NodeTransform, SkeletonNode,
+    Skin,
+};
+
+const EPSILON: f32 = 1e-4;
+
+fn skin() -> Skin {
+    Skin {
+        nodes: vec![SkeletonNode {
+            name: None,
+            parent: None,
+            rest: NodeTransform::default(),
+            weights: Vec::new(),
+        }],
+        joints: Vec::new(),
+    }
+}
+
+fn translation_clip(
+    interpolation: Interpolation,
+    times: Vec<f32>,
+    values: Vec<f32>,
+) -> AnimationClip {
+    AnimationClip {
+        name: String::new(),
+        channels: vec![Channel {
+            node: 0,
+            interpolation,
+            times,
+            keyframes: Keyframes::Translation(
+                values
+                    .into_iter()
+                    .map(|x| Vector3::new(x, 0.0, 0.0))
+                    .collect(),
+            ),
+        }],
+    }
+}
+
+/// Translation along x of the only node after playing `clips[0]` for `time` seconds.
+fn sampled_x(clips: &[AnimationClip], time: f32) -> f32 {
+    let mut player = AnimationPlayer::new();
+    player.play(0).looping = false;
+    player.update(time, clips);
+
+    player.pose(clips, &skin()).transforms[0].translation.x
+}
+
+#[test]
+fn linear_interpolation() {
+    let clips = [translation_clip(
+        Interpolation::Linear,
+        vec![0.0, 1.0, 3.0],
+        vec![0.0, 2.0, 4.0],
+    )];
+
+    assert!((sampled_x(&clips, 0.25) - 0.5).abs() < EPSILON);
+    assert!((sampled_x(&clips, 2.0) - 3.0).abs() < EPSILON);
+}
+
+#[test]
+fn step_interpolation_holds_keyframes() {
+    let clips = [translation_clip(
+        Interpolation::Step,
+        vec![0.0, 1.0],
+        vec![1.0, 5.0],
+    )];
+
+    assert!((sampled_x(&clips, 0.99) - 1.0).abs() < EPSILON);
+    assert!((sampled_x(&clips, 1.0) - 5.0).abs() < EPSILON);
+}
+
+#[test]
+fn cubic_spline_interpolation() {
+    // In-tangent, value and out-tangent of each keyframe
+    let clips = [translation_clip(
+        Interpolation::CubicSpline,
+        vec![0.0, 2.0],
+        vec![0.0, 0.0, 1.0, -1.0, 4.0, 0.0],
+    )];
+
+    // Hermite basis at half way: the first value is zero and the tangents are scaled by the
+    // keyframe interval
+    let expected = 0.125 * (1.0 * 2.0) + 0.5 * 4.0 + 0.125 * (1.0 * 2.0);
+    assert!((sampled_x(&clips, 1.0) - expected).abs() < EPSILON);
+    assert!((sampled_x(&clips, 2.0) - 4.0).abs() < EPSILON);
+}
+
+#[test]
+fn rotations_take_the_shortest_path() {
+    let start = Quaternion::from_angle_y(Deg(0.0));
+    let end = -Quaternion::from_angle_y(Deg(90.0));
+    let clips = [AnimationClip {
+        name: String::new(),
+        channels: vec![Channel {
+            node: 0,
+            interpolation: Interpolation::Linear,
+            times: vec![0.0, 1.0],
+            keyframes: Keyframes::Rotation(vec![start, end]),
+        }],
+    }];
+
+    let mut player = AnimationPlayer::new();
+    player.play(0);
+    player.update(0.5, &clips);
+    let rotation = player.pose(&clips, &skin()).transforms[0].rotation;
+
+    let expected = Quaternion::from_angle_y(Deg(45.0));
+    assert!(rotation.dot(expected).abs() > 1.0 - EPSILON);
+}
+
+#[test]
+fn looping_wraps_and_pausing_stops_time() {
+    let clips = [translation_clip(
+        Interpolation::Linear,
+        vec![0.0, 2.0],
+        vec![0.0, 2.0],
+    )];
+
+    let mut player = AnimationPlayer::new();
+    player.play(0);
+    player.update(2.5, &clips);
+    assert!((player.playing()[0].time - 0.5).abs() < EPSILON);
+
+    player.pause();
+    player.update(1.0, &clips);
+    assert!((player.playing()[0].time - 0.5).abs() < EPSILON);
+
+    player.resume();
+    player.playing_mut(0).unwrap().looping = false;
+    player.update(5.0, &clips);
+    assert!((player.playing()[0].time - 2.0).abs() < EPSILON);
+}
+
+#[test]
+fn blending_mixes_clips_by_weight() {
+    let clips = [
+        translation_clip(Interpolation::Step, vec![0.0], vec![4.0]),
+        translation_clip(Interpolation::Step, vec![0.0], vec![8.0]),
+    ];
+
+    let mut player = AnimationPlayer::new();
+    player.play(0).weight = 3.0;
+    player.blend(1, 1.0);
+
+    let pose = player.pose(&clips, &skin());
+    assert!((pose.transforms[0].translation.x - 5.0).abs() < EPSILON);
+
+    player.stop(0);
+    let pose = player.pose(&clips, &skin());
+    assert!((pose.transforms[0].translation.x - 8.0).abs() < EPSILON);
+}
+
+#[test]
+fn weight_channels_drive_every_target() {
+    let mut skin = skin();
+    skin.nodes[0].weights = vec![0.25, 0.0];
+
+    // Two targets per keyframe
+    let clip = AnimationClip {
+        name: String::new(),
+        channels: vec![Channel {
+            node: 0,
+            interpolation: Interpolation::Linear,
+            times: vec![0.0, 1.0],
+            keyframes: Keyframes::Weights(vec![0.0, 1.0, 1.0, 0.0]),
+        }],
+    };
+    let clips = [
+        clip,
+        translation_clip(Interpolation::Step, vec![0.0], vec![0.0]),
+    ];
+
+    let mut player = AnimationPlayer::new();
+    assert_eq!(player.pose(&clips, &skin).weights[0], vec![0.25, 0.0]);
+
+    player.play(0);
+    player.update(0.25, &clips);
+    let weights = &player.pose(&clips, &skin).weights[0];
+    assert!((weights[0] - 0.25).abs() < EPSILON);
+    assert!((weights[1] - 0.75).abs() < EPSILON);
+
+    // Clips that don't animate the weights blend in the rest ones
+    player.blend(1, 1.0);
+    let weights = &player.pose(&clips, &skin).weights[0];
+    assert!((weights[0] - 0.25).abs() < EPSILON);
+    assert!((weights[1] - 0.375).abs() < EPSILON);
+}
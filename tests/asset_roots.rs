@@ -0,0 +1,50 @@
+use std::{path::PathBuf, sync::Mutex};
+
+use wgpu_renderer::{asset_root_from_args, resolve_asset, set_asset_roots, ASSET_ROOTS_ENV};
+
+/// Held by the tests that set or search the roots, which are global to the process.
+static ROOTS: Mutex<()> = Mutex::new(());
+
+#[test]
+fn asset_root_comes_from_the_command_line_flag() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(
+        asset_root_from_args(args(&["--fullscreen", "--assets", "/opt/game"])),
+        Some(PathBuf::from("/opt/game"))
+    );
+    assert_eq!(
+        asset_root_from_args(args(&["--assets=data"])),
+        Some(PathBuf::from("data"))
+    );
+    assert_eq!(asset_root_from_args(args(&["--assets"])), None);
+    assert_eq!(asset_root_from_args(args(&["--assetsdir"])), None);
+}
+
+#[test]
+fn roots_set_by_the_application_are_searched_first() {
+    let _roots = ROOTS.lock().unwrap();
+    // The default roots have a cube.mtl too, in the repository's res/
+    let default = resolve_asset("cube.mtl").unwrap();
+
+    let root = std::env::temp_dir().join(format!("wgpu-renderer-assets-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("cube.mtl"), "").unwrap();
+
+    set_asset_roots([&root]);
+    let resolved = resolve_asset("cube.mtl");
+    set_asset_roots(Vec::<PathBuf>::new());
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_ne!(default, root.join("cube.mtl"));
+    assert_eq!(resolved.unwrap(), root.join("cube.mtl"));
+}
+
+#[test]
+fn missing_assets_report_where_they_were_looked_for() {
+    let _roots = ROOTS.lock().unwrap();
+    let error = resolve_asset("missing.obj").unwrap_err().to_string();
+
+    assert!(error.contains("\"missing.obj\""), "{}", error);
+    assert!(error.contains(ASSET_ROOTS_ENV), "{}", error);
+}
@@ -0,0 +1,310 @@
+use wgpu_renderer::{Assets, GraphicsContext, Material, Texture};
+
+mod common;
+
+fn context() -> Option<GraphicsContext<'static>> {
+    common::context_with_size(1, 1)
+}
+
+#[test]
+fn loading_a_path_twice_shares_the_asset() {
+    let Some(context) = context() else {
+        return;
+    };
+    let assets = Assets::new();
+
+    let first = assets
+        .load_model("cube.obj", &context.device, &context.queue)
+        .unwrap();
+    let second = assets
+        .load_model("cube.obj", &context.device, &context.queue)
+        .unwrap();
+
+    assert_eq!(first, second);
+    assert_eq!(assets.model_count(), 1);
+}
+
+#[test]
+fn loading_a_path_from_several_threads_shares_the_asset() {
+    let Some(context) = context() else {
+        return;
+    };
+    let assets = Assets::new();
+
+    let handles = std::thread::scope(|scope| {
+        let loads = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    assets
+                        .load_model("cube.obj", &context.device, &context.queue)
+                        .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+        loads
+            .into_iter()
+            .map(|load| load.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    assert!(handles.iter().all(|handle| *handle == handles[0]));
+    assert_eq!(assets.model_count(), 1);
+}
+
+#[test]
+fn models_share_textures_loaded_from_the_same_file() {
+    let Some(context) = context() else {
+        return;
+    };
+    let assets = Assets::new();
+
+    let cube = assets
+        .load_model("cube.obj", &context.device, &context.queue)
+        .unwrap();
+    let shapes = assets
+        .load_model("shapes.obj", &context.device, &context.queue)
+        .unwrap();
+    let texture = assets
+        .load_texture("cube-diffuse.jpg", &context.device, &context.queue)
+        .unwrap();
+
+    let shapes = shapes.get();
+    let textured = shapes
+        .materials
+        .iter()
+        .find(|material| material.name == "Textured")
+        .expect("Missing textured material");
+
+    assert_eq!(cube.get().materials[0].texture, texture);
+    assert_eq!(textured.texture, texture);
+    // And the normal map of the cube
+    assert_eq!(assets.texture_count(), 2);
+}
+
+#[test]
+fn assets_are_freed_with_their_last_handle() {
+    let Some(context) = context() else {
+        return;
+    };
+    let assets = Assets::new();
+
+    let model = assets
+        .load_model("cube.obj", &context.device, &context.queue)
+        .unwrap();
+    let texture = model.get().materials[0].texture.clone();
+    drop(model);
+
+    // The texture is still used outside of the model, unlike its normal map
+    assert_eq!(assets.model_count(), 0);
+    assert_eq!(assets.texture_count(), 1);
+    assert_eq!(texture.count(), 1);
+
+    drop(texture);
+    assert_eq!(assets.texture_count(), 0);
+
+    let reloaded = assets
+        .load_model("cube.obj", &context.device, &context.queue)
+        .unwrap();
+    assert_eq!(assets.model_count(), 1);
+    assert_eq!(assets.texture_count(), 2);
+    assert_eq!(reloaded.get().materials[0].texture.count(), 1);
+}
+
+#[test]
+fn gltf_materials_keep_their_metallic_roughness_parameters() {
+    let Some(context) = context() else {
+        return;
+    };
+    let assets = Assets::new();
+
+    let model = assets
+        .load_model("pbr.gltf", &context.device, &context.queue)
+        .unwrap();
+    let model = model.get();
+    let material = |name| {
+        model
+            .materials
+            .iter()
+            .find(|material: &&Material| material.name == name)
+            .unwrap_or_else(|| panic!("Missing material {:?}", name))
+    };
+
+    let gold = material("gold");
+    assert_eq!(gold.base_color, [1.0, 0.766, 0.336]);
+    assert_eq!((gold.metallic, gold.roughness), (1.0, 0.3));
+    assert!(gold.metallic_roughness_texture.is_none());
+
+    // The same image is a color texture and a data one, which are different textures
+    let lamp = material("lamp");
+    let metallic_roughness = lamp.metallic_roughness_texture.as_ref().unwrap();
+    let occlusion = lamp.occlusion_texture.as_ref().unwrap();
+    let emissive = lamp.emissive_texture.as_ref().unwrap();
+    assert_eq!(metallic_roughness, occlusion);
+    assert_eq!(*emissive, lamp.texture);
+    assert_ne!(*metallic_roughness, lamp.texture);
+    assert_eq!(
+        metallic_roughness.get().texture.format(),
+        Texture::LINEAR_FORMAT
+    );
+    assert_eq!(emissive.get().texture.format(), Texture::COLOR_FORMAT);
+
+    assert_eq!(lamp.normal_scale, 0.5);
+    assert_eq!(lamp.occlusion_strength, 0.75);
+    assert_eq!(lamp.emissive, [0.6, 0.3, 0.05]);
+}
+
+#[test]
+fn mtl_materials_map_to_metallic_roughness() {
+    let Some(context) = context() else {
+        return;
+    };
+    let assets = Assets::new();
+
+    let model = assets
+        .load_model("pbr.obj", &context.device, &context.queue)
+        .unwrap();
+    let model = model.get();
+    let [metal, glowing] = &model.materials[..] else {
+        panic!("Expected 2 materials");
+    };
+
+    // The PBR extension wins over the specular exponent
+    assert_eq!(metal.base_color, [0.9, 0.9, 0.9]);
+    assert_eq!((metal.metallic, metal.roughness), (1.0, 0.2));
+
+    // Bump maps are tangent space normal maps
+    let normal_map = assets
+        .load_linear_texture("cube-normal.png", &context.device, &context.queue)
+        .unwrap();
+    assert_eq!(metal.normal_texture.as_ref(), Some(&normal_map));
+    assert_eq!(metal.normal_scale, 0.5);
+    assert_eq!(normal_map.get().texture.format(), Texture::LINEAR_FORMAT);
+    assert!(glowing.normal_texture.is_none());
+
+    assert_eq!(glowing.metallic, Material::DEFAULT_METALLIC);
+    assert_eq!(glowing.roughness, Material::roughness_from_shininess(8.0));
+    assert_eq!(glowing.emissive, [1.0, 0.5, 0.0]);
+    let texture = assets
+        .load_texture("textures/test.png", &context.device, &context.queue)
+        .unwrap();
+    assert_eq!(glowing.emissive_texture.as_ref(), Some(&texture));
+}
+
+#[test]
+fn obj_files_report_why_their_materials_failed_to_load() {
+    let Some(context) = context() else {
+        return;
+    };
+    let directory = std::env::temp_dir().join(format!("wgpu-renderer-mtl-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let obj = directory.join("triangle.obj");
+    std::fs::write(
+        &obj,
+        "mtllib missing.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl Missing\nf 1 2 3\n",
+    )
+    .unwrap();
+
+    let result = Assets::new().load_model(&obj.to_string_lossy(), &context.device, &context.queue);
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let error = format!("{:#}", result.unwrap_err());
+    assert!(error.contains("Couldn't load the materials"), "{}", error);
+    assert!(error.contains("missing.mtl"), "{}", error);
+}
+
+#[test]
+fn vertices_on_mirrored_texture_seams_are_split_for_their_tangents() {
+    let Some(context) = context() else {
+        return;
+    };
+    let directory = std::env::temp_dir().join(format!("wgpu-renderer-seam-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    // Two triangles sharing an edge, the second one mapped either onward or mirrored
+    let vertex_buffer_size = |name: &str, last_u: f32| {
+        let obj = directory.join(name);
+        std::fs::write(
+            &obj,
+            format!(
+                "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 2 0 0\n\
+                 vt 0 0\nvt 1 0\nvt 1 1\nvt {} 0\nvn 0 0 1\n\
+                 f 1/1/1 2/2/1 3/3/1\nf 2/2/1 4/4/1 3/3/1\n",
+                last_u
+            ),
+        )
+        .unwrap();
+        let model = Assets::new()
+            .load_model(&obj.to_string_lossy(), &context.device, &context.queue)
+            .unwrap();
+        model.get().meshes[0].0.vertex_buffer.size()
+    };
+    let onward = vertex_buffer_size("onward.obj", 2.0);
+    let mirrored = vertex_buffer_size("mirrored.obj", 0.0);
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    // The 2 vertices of the seam get a copy each for the tangent of the mirrored side
+    assert_eq!(mirrored * 4, onward * 6);
+}
+
+#[test]
+fn gltf_textures_outside_of_their_buffer_fail_to_load() {
+    let Some(context) = context() else {
+        return;
+    };
+    let directory =
+        std::env::temp_dir().join(format!("wgpu-renderer-gltf-view-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    // A triangle whose base color image claims more bytes than its buffer has
+    let gltf = directory.join("broken.gltf");
+    let zeros = "AAAA".repeat(12);
+    std::fs::write(
+        &gltf,
+        format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scene": 0,
+                "scenes": [{{ "nodes": [0] }}],
+                "nodes": [{{ "mesh": 0 }}],
+                "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "material": 0 }}] }}],
+                "materials": [{{ "pbrMetallicRoughness": {{ "baseColorTexture": {{ "index": 0 }} }} }}],
+                "textures": [{{ "source": 0 }}],
+                "images": [{{ "bufferView": 1, "mimeType": "image/png" }}],
+                "accessors": [{{
+                    "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                    "min": [0, 0, 0], "max": [0, 0, 0]
+                }}],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteLength": 36 }},
+                    {{ "buffer": 0, "byteOffset": 32, "byteLength": 64 }}
+                ],
+                "buffers": [{{ "byteLength": 36, "uri": "data:application/octet-stream;base64,{}" }}]
+            }}"#,
+            zeros
+        ),
+    )
+    .unwrap();
+
+    let result = Assets::new().load_model(&gltf.to_string_lossy(), &context.device, &context.queue);
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn obj_files_without_texture_coordinates_load() {
+    let Some(context) = context() else {
+        return;
+    };
+    let directory =
+        std::env::temp_dir().join(format!("wgpu-renderer-no-uv-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let obj = directory.join("triangle.obj");
+    std::fs::write(&obj, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+
+    let result = Assets::new().load_model(&obj.to_string_lossy(), &context.device, &context.queue);
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let model = result.unwrap();
+    assert_eq!(model.get().meshes[0].0.index_count, 3);
+}
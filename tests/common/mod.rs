@@ -0,0 +1,252 @@
+// Shared by several test crates, each of which only uses part of it
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use cgmath::Deg;
+use wgpu_renderer::{
+    load_model, Attenuation, Camera, CameraDescriptor, Entity, GraphicsContext, Light, Pass,
+    PbrPass, Position, RenderGraph, Scale, Transform,
+};
+
+pub const WIDTH: u32 = 256;
+pub const HEIGHT: u32 = 256;
+
+/// Largest difference allowed in any channel before a pixel counts as mismatched.
+pub const CHANNEL_TOLERANCE: u8 = 8;
+
+/// Share of mismatched pixels a comparison still accepts, to absorb rasterization differences
+/// between adapters.
+pub const MAX_MISMATCHED_RATIO: f32 = 0.005;
+
+/// Set to skip the tests that need an adapter on machines without one, instead of failing them.
+pub const SKIP_GPU_TESTS_ENV: &str = "WGPU_RENDERER_SKIP_GPU_TESTS";
+
+/// Creates a headless context the size of the golden images. See `context_with_size`.
+pub fn context() -> Option<GraphicsContext<'static>> {
+    context_with_size(WIDTH, HEIGHT)
+}
+
+/// Creates a headless context. A machine without an adapter fails the test, unless
+/// `WGPU_RENDERER_SKIP_GPU_TESTS` is set, which returns `None` for the test to skip itself.
+pub fn context_with_size(width: u32, height: u32) -> Option<GraphicsContext<'static>> {
+    match pollster::block_on(GraphicsContext::new_headless(width, height)) {
+        Ok(context) => Some(context),
+        Err(error) if skip_gpu_tests() => {
+            eprintln!("Skipping GPU test: {}", error);
+            None
+        }
+        Err(error) => panic!(
+            "No adapter to run the GPU tests, set {}=1 to skip them: {}",
+            SKIP_GPU_TESTS_ENV, error
+        ),
+    }
+}
+
+fn skip_gpu_tests() -> bool {
+    std::env::var(SKIP_GPU_TESTS_ENV).is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+pub fn camera() -> Camera {
+    Camera::new(
+        (0.0, 2.0, 4.0),
+        Deg(-90.0),
+        Deg(-20.0),
+        CameraDescriptor {
+            aspect: WIDTH as f32 / HEIGHT as f32,
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0,
+        },
+    )
+}
+
+/// Default lighting for test scenes: a dim directional light and a warm point light.
+pub fn lights() -> Vec<Entity> {
+    let sun = Entity::builder()
+        .light(Light {
+            intensity: 0.5,
+            ..Light::directional([-0.3, -1.0, -0.5])
+        })
+        .build();
+
+    let lamp = Entity::builder()
+        .light(Light {
+            color: [1.0, 0.8, 0.6],
+            ..Light::point(Attenuation::default())
+        })
+        .transform(Transform {
+            position: Position(2.0, 2.0, 1.0),
+            ..Default::default()
+        })
+        .build();
+
+    vec![sun, lamp]
+}
+
+/// The textured cube of `cube.obj` in front of the camera, where most test scenes put it.
+pub fn cube(context: &GraphicsContext) -> Entity {
+    cube_at(context, Position(0.0, 0.0, -1.0), 1.0)
+}
+
+/// The textured cube of `cube.obj` at `position`, scaled uniformly by `scale`.
+pub fn cube_at(context: &GraphicsContext, position: Position, scale: f32) -> Entity {
+    Entity::builder()
+        .model(load_model("cube.obj", &context.device, &context.queue).unwrap())
+        .transform(Transform {
+            position,
+            scale: Scale(scale, scale, scale),
+            ..Default::default()
+        })
+        .build()
+}
+
+pub fn render(context: &GraphicsContext, entities: &[Entity], camera: &Camera) -> image::RgbaImage {
+    render_frames(context, &[entities], camera)
+}
+
+/// Draws each list of entities in its own frame with the same pass, and captures the last one.
+pub fn render_frames(
+    context: &GraphicsContext,
+    frames: &[&[Entity]],
+    camera: &Camera,
+) -> image::RgbaImage {
+    let mut pass = PbrPass::new(&context.device, &context.config).unwrap();
+
+    let mut image = None;
+    for entities in frames {
+        image = Some(draw(context, &mut pass, entities, camera));
+    }
+
+    image.expect("No frames to render")
+}
+
+/// Draws one frame with `pass` and captures it.
+pub fn draw(
+    context: &GraphicsContext,
+    pass: &mut PbrPass,
+    entities: &[Entity],
+    camera: &Camera,
+) -> image::RgbaImage {
+    let GraphicsContext {
+        target,
+        device,
+        queue,
+        ..
+    } = context;
+
+    let frame = target.current_frame();
+    pass.draw(
+        &mut RenderGraph::new(),
+        &frame,
+        device,
+        queue,
+        entities,
+        camera,
+    )
+    .expect("Error when drawing frame");
+    let image = frame
+        .capture(device, queue)
+        .expect("Error when reading back frame");
+    frame.present();
+
+    image
+}
+
+pub struct Comparison {
+    pub mismatched: usize,
+    pub diff: image::RgbaImage,
+}
+
+impl Comparison {
+    pub fn passed(&self) -> bool {
+        self.mismatched as f32
+            <= MAX_MISMATCHED_RATIO * (self.diff.width() * self.diff.height()) as f32
+    }
+}
+
+/// Compares two images pixel by pixel. Mismatched pixels are painted red in the diff image and
+/// the rest are a faded copy of the expected image.
+pub fn compare(
+    actual: &image::RgbaImage,
+    expected: &image::RgbaImage,
+    tolerance: u8,
+) -> Comparison {
+    assert_eq!(
+        actual.dimensions(),
+        expected.dimensions(),
+        "Rendered image and reference have different sizes"
+    );
+
+    let mut mismatched = 0;
+    let diff = image::RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let a = actual.get_pixel(x, y);
+        let e = expected.get_pixel(x, y);
+
+        let matches =
+            a.0.iter()
+                .zip(e.0.iter())
+                .all(|(a, e)| a.abs_diff(*e) <= tolerance);
+
+        if matches {
+            let [r, g, b, _] = e.0;
+            image::Rgba([r / 4, g / 4, b / 4, 255])
+        } else {
+            mismatched += 1;
+            image::Rgba([255, 0, 0, 255])
+        }
+    });
+
+    Comparison { mismatched, diff }
+}
+
+fn reference_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.png", name))
+}
+
+fn output_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+/// Checks a rendered image against `tests/golden/<name>.png`. Setting `UPDATE_GOLDEN=1` writes
+/// the rendered image as the new reference instead. On failure the rendered and diff images are
+/// written next to each other in the test output directory.
+pub fn assert_golden(name: &str, actual: &image::RgbaImage) {
+    let reference = reference_path(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        actual
+            .save(&reference)
+            .expect("Error when writing reference image");
+        return;
+    }
+
+    let expected = image::open(&reference)
+        .unwrap_or_else(|error| panic!("Missing reference image {:?}: {}", reference, error))
+        .to_rgba8();
+
+    let comparison = compare(actual, &expected, CHANNEL_TOLERANCE);
+    if comparison.passed() {
+        return;
+    }
+
+    let output_dir = output_dir();
+    std::fs::create_dir_all(&output_dir).expect("Error when creating golden output directory");
+    let actual_path = output_dir.join(format!("{}-actual.png", name));
+    let diff_path = output_dir.join(format!("{}-diff.png", name));
+    actual
+        .save(&actual_path)
+        .expect("Error when writing rendered image");
+    comparison
+        .diff
+        .save(&diff_path)
+        .expect("Error when writing diff image");
+
+    panic!(
+        "{} differs from its reference in {} pixels, see {:?} and {:?}",
+        name, comparison.mismatched, actual_path, diff_path
+    );
+}
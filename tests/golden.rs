@@ -0,0 +1,894 @@
+mod common;
+
+use cgmath::Deg;
+use wgpu_renderer::{
+    load_model, AnimationPlayer, Assets, Attenuation, Entity, GraphicsContext, Handle, Instance,
+    Light, Model, PbrPass, Position, RenderPath, Scale, ShadowSettings, Transform,
+};
+
+#[test]
+fn cube_primitive() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let cube = Entity::builder()
+        .model(
+            Model::cube(&context.device, &context.queue, "textures/test.png")
+                .expect("Error when creating cube"),
+        )
+        .transform(Transform {
+            position: Position(0.0, 0.0, -1.0),
+            scale: Scale(1.0, 1.0, 1.0),
+            ..Default::default()
+        })
+        .build();
+
+    let mut entities = common::lights();
+    entities.push(cube);
+
+    let image = common::render(&context, &entities, &common::camera());
+
+    common::assert_golden("cube_primitive", &image);
+}
+
+#[test]
+fn cube_obj() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let cube = common::cube(&context);
+
+    let mut entities = common::lights();
+    entities.push(cube);
+
+    let image = common::render(&context, &entities, &common::camera());
+
+    common::assert_golden("cube_obj", &image);
+}
+
+/// The cube lit by a spot light from above the camera.
+fn spot_light_scene(
+    context: &GraphicsContext,
+    inner_cutoff: f32,
+    outer_cutoff: f32,
+) -> Vec<Entity> {
+    let spot = Entity::builder()
+        .light(Light {
+            intensity: 2.0,
+            ..Light::spot(
+                [0.0, -0.4, -1.0],
+                Deg(inner_cutoff),
+                Deg(outer_cutoff),
+                Attenuation::default(),
+            )
+        })
+        .transform(Transform {
+            position: Position(0.0, 1.0, 2.0),
+            ..Default::default()
+        })
+        .build();
+
+    vec![common::cube(context), spot]
+}
+
+#[test]
+fn spot_light() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let entities = spot_light_scene(&context, 10.0, 15.0);
+    let image = common::render(&context, &entities, &common::camera());
+
+    common::assert_golden("spot_light", &image);
+}
+
+#[test]
+fn spot_light_with_equal_cutoffs_has_a_hard_edge() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let camera = common::camera();
+    let hard = common::render(&context, &spot_light_scene(&context, 12.5, 12.5), &camera);
+    let almost_hard = common::render(&context, &spot_light_scene(&context, 12.49, 12.5), &camera);
+    let comparison = common::compare(&hard, &almost_hard, common::CHANNEL_TOLERANCE);
+    assert!(
+        comparison.passed(),
+        "{} pixels differ",
+        comparison.mismatched
+    );
+}
+
+#[test]
+fn comparison_counts_pixels_outside_tolerance() {
+    let expected = image::RgbaImage::from_pixel(4, 4, image::Rgba([100, 100, 100, 255]));
+    let mut actual = expected.clone();
+    actual.put_pixel(
+        0,
+        0,
+        image::Rgba([100, 100, 100 + common::CHANNEL_TOLERANCE, 255]),
+    );
+    actual.put_pixel(
+        1,
+        0,
+        image::Rgba([100, 100, 101 + common::CHANNEL_TOLERANCE, 255]),
+    );
+
+    let comparison = common::compare(&actual, &expected, common::CHANNEL_TOLERANCE);
+
+    assert_eq!(comparison.mismatched, 1);
+    assert_eq!(
+        comparison.diff.get_pixel(1, 0),
+        &image::Rgba([255, 0, 0, 255])
+    );
+    assert!(!comparison.passed());
+}
+
+#[test]
+fn directional_shadow() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let cube = common::cube_at(&context, Position(0.0, 0.0, -1.0), 0.5);
+
+    let floor = Entity::builder()
+        .model(
+            Model::cube(&context.device, &context.queue, "textures/test.png")
+                .expect("Error when creating cube"),
+        )
+        .transform(Transform {
+            position: Position(0.0, -0.6, -1.0),
+            scale: Scale(3.0, 0.1, 3.0),
+            ..Default::default()
+        })
+        .build();
+
+    let sun = Entity::builder()
+        .light(Light::directional([-0.5, -1.0, -0.3]))
+        .build();
+
+    let image = common::render(&context, &[floor, cube, sun], &common::camera());
+
+    common::assert_golden("directional_shadow", &image);
+}
+
+#[test]
+fn cascaded_shadows() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let floor = Entity::builder()
+        .model(
+            Model::cube(&context.device, &context.queue, "textures/test.png")
+                .expect("Error when creating cube"),
+        )
+        .transform(Transform {
+            position: Position(0.0, -1.1, -30.0),
+            scale: Scale(10.0, 0.1, 35.0),
+            ..Default::default()
+        })
+        .build();
+
+    let mut entities = vec![floor];
+    for i in 0..6 {
+        let z = -1.0 - i as f32 * i as f32 * 1.6;
+        entities.push(common::cube_at(
+            &context,
+            Position(if i % 2 == 0 { -1.5 } else { 1.5 }, 0.0, z),
+            1.0,
+        ));
+    }
+
+    entities.push(
+        Entity::builder()
+            .light(Light::directional([-0.6, -1.0, -0.2]))
+            .build(),
+    );
+
+    let image = common::render(&context, &entities, &common::camera());
+
+    common::assert_golden("cascaded_shadows", &image);
+}
+
+#[test]
+fn point_light_shadow() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let cube = common::cube_at(&context, Position(0.0, -0.2, -1.0), 0.3);
+
+    let floor = Entity::builder()
+        .model(
+            Model::cube(&context.device, &context.queue, "textures/test.png")
+                .expect("Error when creating cube"),
+        )
+        .transform(Transform {
+            position: Position(0.0, -0.6, -1.0),
+            scale: Scale(3.0, 0.1, 3.0),
+            ..Default::default()
+        })
+        .build();
+
+    let mut lamp = Light::point(Attenuation::default());
+    lamp.cast_shadows = true;
+    lamp.color = [1.0, 0.9, 0.7];
+    let lamp = Entity::builder()
+        .light(lamp)
+        .transform(Transform {
+            position: Position(0.8, 0.8, -0.6),
+            ..Default::default()
+        })
+        .build();
+
+    let image = common::render(&context, &[floor, cube, lamp], &common::camera());
+
+    common::assert_golden("point_light_shadow", &image);
+}
+
+/// Floor with a cube on it, lit by a sun and a lamp that both cast shadows.
+fn shadowed_scene(context: &GraphicsContext) -> Vec<Entity> {
+    let cube = common::cube_at(context, Position(0.0, -0.2, -1.0), 0.3);
+
+    let floor = Entity::builder()
+        .model(
+            Model::cube(&context.device, &context.queue, "textures/test.png")
+                .expect("Error when creating cube"),
+        )
+        .transform(Transform {
+            position: Position(0.0, -0.6, -1.0),
+            scale: Scale(3.0, 0.1, 3.0),
+            ..Default::default()
+        })
+        .build();
+
+    let mut entities = common::lights();
+    for light in &mut entities {
+        light.light.as_mut().unwrap().cast_shadows = true;
+    }
+    entities.extend([floor, cube]);
+    entities
+}
+
+#[test]
+fn deferred_renders_like_forward() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let entities = shadowed_scene(&context);
+    let camera = common::camera();
+    let forward = common::render(&context, &entities, &camera);
+
+    let mut pass = PbrPass::new(&context.device, &context.config).unwrap();
+    pass.set_render_path(RenderPath::Deferred);
+    let deferred = common::draw(&context, &mut pass, &entities, &camera);
+
+    let comparison = common::compare(&deferred, &forward, common::CHANNEL_TOLERANCE);
+    assert!(
+        comparison.passed(),
+        "{} pixels differ",
+        comparison.mismatched
+    );
+}
+
+/// Floor lit by a 16 by 16 grid of small colored lamps just above it.
+fn many_lights_scene(context: &GraphicsContext) -> Vec<Entity> {
+    let floor = Entity::builder()
+        .model(
+            Model::cube(&context.device, &context.queue, "cube-diffuse.jpg")
+                .expect("Error when creating cube"),
+        )
+        .transform(Transform {
+            position: Position(0.0, -0.6, -2.0),
+            scale: Scale(4.0, 0.1, 4.0),
+            ..Default::default()
+        })
+        .build();
+
+    let mut entities = vec![floor];
+    for i in 0..256 {
+        let (x, z) = ((i % 16) as f32, (i / 16) as f32);
+        let color = [x / 15.0, 1.0 - z / 15.0, 0.5];
+        entities.push(
+            Entity::builder()
+                .light(Light {
+                    color,
+                    cast_shadows: false,
+                    ..Light::point(Attenuation {
+                        constant: 1.0,
+                        linear: 4.0,
+                        quadratic: 40.0,
+                    })
+                })
+                .transform(Transform {
+                    position: Position(x * 0.25 - 1.9, -0.4, z * 0.25 - 3.9),
+                    ..Default::default()
+                })
+                .build(),
+        );
+    }
+
+    entities
+}
+
+#[test]
+fn deferred_many_lights() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let entities = many_lights_scene(&context);
+    let mut pass = PbrPass::with_max_lights(&context.device, &context.config, 256).unwrap();
+    pass.set_render_path(RenderPath::Deferred);
+    let image = common::draw(&context, &mut pass, &entities, &common::camera());
+
+    common::assert_golden("deferred_many_lights", &image);
+}
+
+#[test]
+fn clustered_renders_like_forward() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let entities = shadowed_scene(&context);
+    let camera = common::camera();
+    let forward = common::render(&context, &entities, &camera);
+
+    let mut pass = PbrPass::new(&context.device, &context.config).unwrap();
+    pass.set_render_path(RenderPath::Clustered);
+    let clustered = common::draw(&context, &mut pass, &entities, &camera);
+
+    let comparison = common::compare(&clustered, &forward, common::CHANNEL_TOLERANCE);
+    assert!(
+        comparison.passed(),
+        "{} pixels differ",
+        comparison.mismatched
+    );
+}
+
+#[test]
+fn clustered_many_lights_render_like_deferred() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let entities = many_lights_scene(&context);
+    let camera = common::camera();
+    let mut pass = PbrPass::with_max_lights(&context.device, &context.config, 256).unwrap();
+
+    pass.set_render_path(RenderPath::Deferred);
+    let deferred = common::draw(&context, &mut pass, &entities, &camera);
+    pass.set_render_path(RenderPath::Clustered);
+    let clustered = common::draw(&context, &mut pass, &entities, &camera);
+
+    let comparison = common::compare(&clustered, &deferred, common::CHANNEL_TOLERANCE);
+    assert!(
+        comparison.passed(),
+        "{} pixels differ",
+        comparison.mismatched
+    );
+}
+
+#[test]
+fn light_limits_past_the_device_limits_are_rejected() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let max_lights = context.device.limits().max_storage_buffer_binding_size as usize;
+    let error = PbrPass::with_max_lights(&context.device, &context.config, max_lights)
+        .err()
+        .expect("The lights fit in a storage buffer");
+
+    assert!(error.to_string().contains("storage buffer"), "{:#}", error);
+}
+
+#[test]
+fn clustered_transparency_with_msaa() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    // Two panes of glass in front of a crate, added front to back so they have to be sorted
+    let glass = |texture, opacity, position| {
+        let mut model = Model::cube(&context.device, &context.queue, texture)
+            .expect("Error when creating cube");
+        model.materials[0].opacity = opacity;
+
+        Entity::builder()
+            .model(model)
+            .transform(Transform {
+                position,
+                scale: Scale(0.25, 0.25, 0.01),
+                ..Default::default()
+            })
+            .build()
+    };
+    let crate_box = common::cube_at(&context, Position(0.0, 0.75, 0.6), 0.4);
+
+    let mut entities = common::lights();
+    entities.extend([
+        glass("textures/test.png", 0.5, Position(0.15, 1.2, 1.9)),
+        glass("cube-diffuse.jpg", 0.6, Position(-0.15, 1.1, 1.45)),
+        crate_box,
+    ]);
+
+    let mut pass = PbrPass::new(&context.device, &context.config).unwrap();
+    pass.set_render_path(RenderPath::Clustered);
+    pass.set_sample_count(&context.adapter, &context.device, 4)
+        .unwrap();
+    let image = common::draw(&context, &mut pass, &entities, &common::camera());
+
+    common::assert_golden("clustered_transparency_with_msaa", &image);
+}
+
+#[test]
+fn invalid_shadow_settings_are_rejected() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let mut pass = PbrPass::new(&context.device, &context.config).unwrap();
+    let max_resolution = context.device.limits().max_texture_dimension_2d;
+    let defaults = ShadowSettings::default();
+    for settings in [
+        ShadowSettings {
+            resolution: 0,
+            ..defaults
+        },
+        ShadowSettings {
+            resolution: max_resolution + 1,
+            ..defaults
+        },
+        ShadowSettings {
+            point_resolution: 0,
+            ..defaults
+        },
+        ShadowSettings {
+            point_resolution: max_resolution + 1,
+            ..defaults
+        },
+        ShadowSettings {
+            distance: 0.0,
+            ..defaults
+        },
+    ] {
+        assert!(pass.set_shadow_settings(&context.device, settings).is_err());
+    }
+
+    assert_eq!(pass.shadow_settings().resolution, defaults.resolution);
+}
+
+#[test]
+fn unsupported_sample_counts_are_rejected() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let mut pass = PbrPass::new(&context.device, &context.config).unwrap();
+    let error = pass
+        .set_sample_count(&context.adapter, &context.device, 3)
+        .unwrap_err();
+
+    assert!(error.to_string().contains("3 samples"), "{:#}", error);
+    assert_eq!(pass.sample_count(), 1);
+}
+
+#[test]
+fn pbr_materials() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    // A polished metal, a rough dielectric and a textured emissive cube, side by side
+    let cubes = Entity::builder()
+        .model(load_model("pbr.gltf", &context.device, &context.queue).unwrap())
+        .transform(Transform {
+            position: Position(0.0, 0.85, 0.9),
+            scale: Scale(0.6, 0.6, 0.6),
+            ..Default::default()
+        })
+        .build();
+
+    let mut entities = common::lights();
+    entities.push(cubes);
+
+    let image = common::render(&context, &entities, &common::camera());
+    common::assert_golden("pbr_materials", &image);
+}
+
+fn render_boxes(file_name: &str) -> Option<image::RgbaImage> {
+    let context = common::context()?;
+
+    let boxes = Entity::builder()
+        .model(load_model(file_name, &context.device, &context.queue).unwrap())
+        .transform(Transform {
+            scale: Scale(1.0, 1.0, 1.0),
+            ..Default::default()
+        })
+        .build();
+
+    let mut entities = common::lights();
+    entities.push(boxes);
+
+    Some(common::render(&context, &entities, &common::camera()))
+}
+
+#[test]
+fn gltf_model() {
+    if let Some(image) = render_boxes("boxes.gltf") {
+        common::assert_golden("gltf_model", &image);
+    }
+}
+
+#[test]
+fn glb_model() {
+    if let Some(image) = render_boxes("boxes.glb") {
+        common::assert_golden("gltf_model", &image);
+    }
+}
+
+#[test]
+fn skinned_animation() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let tube = load_model("tube.gltf", &context.device, &context.queue).unwrap();
+
+    // Halfway through bending the upper joint by 90 degrees
+    let mut animation = AnimationPlayer::new();
+    animation.play(0);
+    animation.update(0.5, &tube.animations);
+
+    let tube = Entity::builder()
+        .model(tube)
+        .animation(animation)
+        .transform(Transform {
+            position: Position(0.0, -0.8, -1.0),
+            scale: Scale(1.0, 1.0, 1.0),
+            ..Default::default()
+        })
+        .build();
+
+    let mut entities = common::lights();
+    entities.push(tube);
+
+    let image = common::render(&context, &entities, &common::camera());
+
+    common::assert_golden("skinned_animation", &image);
+}
+
+#[test]
+fn morph_targets() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let sheet = |x| Transform {
+        position: Position(x, 0.0, -1.0),
+        scale: Scale(1.0, 1.0, 1.0),
+        ..Default::default()
+    };
+
+    // Halfway through raising the bulge
+    let animated = load_model("sheet.gltf", &context.device, &context.queue).unwrap();
+    let mut animation = AnimationPlayer::new();
+    animation.play(0);
+    animation.update(0.5, &animated.animations);
+
+    let animated = Entity::builder()
+        .model(animated)
+        .animation(animation)
+        .transform(sheet(-0.6))
+        .build();
+
+    // Fully bulged and stretched upwards
+    let weighted = Entity::builder()
+        .model(load_model("sheet.gltf", &context.device, &context.queue).unwrap())
+        .morph_weights(vec![1.0, 1.0])
+        .transform(sheet(0.6))
+        .build();
+
+    let mut entities = common::lights();
+    entities.push(animated);
+    entities.push(weighted);
+
+    let image = common::render(&context, &entities, &common::camera());
+
+    common::assert_golden("morph_targets", &image);
+}
+
+fn shapes(context: &GraphicsContext, x: f32) -> Entity {
+    Entity::builder()
+        .model(load_model("shapes.obj", &context.device, &context.queue).unwrap())
+        .transform(Transform {
+            position: Position(x, -1.0, -1.0),
+            scale: Scale(1.0, 1.0, 1.0),
+            ..Default::default()
+        })
+        .build()
+}
+
+#[test]
+fn multi_mesh_obj() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let mut entities = common::lights();
+    entities.push(shapes(&context, 0.0));
+
+    let image = common::render(&context, &entities, &common::camera());
+
+    common::assert_golden("multi_mesh_obj", &image);
+}
+
+#[test]
+fn changing_entities_between_frames() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let cube = |x| {
+        Entity::builder()
+            .model(Model::cube(&context.device, &context.queue, "cube-diffuse.jpg").unwrap())
+            .transform(Transform {
+                position: Position(x, 0.5, -2.0),
+                scale: Scale(0.5, 0.5, 0.5),
+                ..Default::default()
+            })
+            .build()
+    };
+
+    // Same models in a different order, and with one mesh less
+    let mut first = common::lights();
+    first.push(cube(-1.5));
+    first.push(shapes(&context, 0.0));
+    first.push(cube(1.5));
+
+    let mut second = common::lights();
+    second.push(shapes(&context, 0.0));
+    second.push(cube(1.5));
+
+    let camera = common::camera();
+    let reused = common::render_frames(&context, &[&first, &second], &camera);
+    let fresh = common::render(&context, &second, &camera);
+
+    let comparison = common::compare(&reused, &fresh, 0);
+    assert_eq!(comparison.mismatched, 0);
+}
+
+#[test]
+fn many_entities() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let cube = |x, z| {
+        Entity::builder()
+            .model(Model::cube(&context.device, &context.queue, "cube-diffuse.jpg").unwrap())
+            .transform(Transform {
+                position: Position(x, 0.0, z),
+                scale: Scale(0.1, 0.1, 0.1),
+                ..Default::default()
+            })
+            .build()
+    };
+
+    // More cubes than the locals buffer starts with room for
+    let mut few = common::lights();
+    few.push(cube(0.0, 0.0));
+
+    let mut many = common::lights();
+    for i in 0..12 {
+        for j in 0..12 {
+            many.push(cube(i as f32 * 0.3 - 1.65, j as f32 * -0.3));
+        }
+    }
+
+    let camera = common::camera();
+    let grown = common::render_frames(&context, &[&few, &many], &camera);
+
+    common::assert_golden("many_entities", &grown);
+}
+
+#[test]
+fn instanced_forest() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let cube =
+        Handle::new(Model::cube(&context.device, &context.queue, "cube-diffuse.jpg").unwrap());
+
+    // A grid of tinted copies drawn through one entity
+    let instances = (0..5)
+        .flat_map(|i| (0..5).map(move |j| (i, j)))
+        .map(|(i, j)| Instance {
+            transform: Transform {
+                position: Position(i as f32 * 0.5 - 1.0, 0.0, j as f32 * -0.5),
+                scale: Scale(0.15, 0.15 + 0.05 * j as f32, 0.15),
+                ..Default::default()
+            },
+            tint: [1.0, 0.5 + 0.1 * i as f32, 0.5],
+        })
+        .collect();
+
+    let grove = Entity::builder()
+        .model(cube.clone())
+        .instances(instances)
+        .transform(Transform {
+            position: Position(0.0, -0.5, -1.0),
+            scale: Scale(1.0, 1.0, 1.0),
+            ..Default::default()
+        })
+        .build();
+
+    let mut entities = common::lights();
+    entities.push(grove);
+
+    // Entities sharing the model join the batch of the grove
+    for x in [-1.5, 1.5] {
+        entities.push(
+            Entity::builder()
+                .model(cube.clone())
+                .transform(Transform {
+                    position: Position(x, 0.5, -2.5),
+                    scale: Scale(0.3, 0.3, 0.3),
+                    ..Default::default()
+                })
+                .build(),
+        );
+    }
+
+    let image = common::render(&context, &entities, &common::camera());
+
+    common::assert_golden("instanced_forest", &image);
+}
+
+#[test]
+fn shared_models_render_like_separate_ones() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    let cube = || Model::cube(&context.device, &context.queue, "cube-diffuse.jpg").unwrap();
+    let shared = Handle::new(cube());
+
+    let transform = |x| Transform {
+        position: Position(x, 0.0, -1.0),
+        scale: Scale(0.4, 0.4, 0.4),
+        ..Default::default()
+    };
+
+    let mut separate = common::lights();
+    let mut batched = common::lights();
+    for x in [-1.0, 0.0, 1.0] {
+        separate.push(
+            Entity::builder()
+                .model(cube())
+                .transform(transform(x))
+                .build(),
+        );
+        batched.push(
+            Entity::builder()
+                .model(shared.clone())
+                .transform(transform(x))
+                .build(),
+        );
+    }
+
+    let camera = common::camera();
+    let separate = common::render(&context, &separate, &camera);
+    let batched = common::render(&context, &batched, &camera);
+
+    assert_eq!(common::compare(&batched, &separate, 0).mismatched, 0);
+}
+
+#[test]
+fn reloaded_shaders_rebuild_the_pipeline() {
+    let Some(context) = common::context() else {
+        return;
+    };
+
+    // Shaders edited in a copy of the shader directory
+    let directory =
+        std::env::temp_dir().join(format!("wgpu-renderer-shaders-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    for entry in
+        std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src/pass/shaders")).unwrap()
+    {
+        let path = entry.unwrap().path();
+        std::fs::copy(&path, directory.join(path.file_name().unwrap())).unwrap();
+    }
+    let shader_path = directory.join("shader.wgsl");
+    let original = std::fs::read_to_string(&shader_path).unwrap();
+
+    let cube = Entity::builder()
+        .model(Model::cube(&context.device, &context.queue, "cube-diffuse.jpg").unwrap())
+        .transform(Transform {
+            position: Position(0.0, 0.0, -1.0),
+            scale: Scale(1.0, 1.0, 1.0),
+            ..Default::default()
+        })
+        .build();
+    let mut entities = common::lights();
+    entities.push(cube);
+    let camera = common::camera();
+
+    let assets = Assets::new();
+    let mut pass = PbrPass::new(&context.device, &context.config).unwrap();
+    pass.watch_shaders(&assets, &directory).unwrap();
+
+    // Unchanged shaders from disk render like the builtin ones
+    let from_disk = common::draw(&context, &mut pass, &entities, &camera);
+    let builtin = common::render(&context, &entities, &camera);
+    assert_eq!(common::compare(&from_disk, &builtin, 0).mismatched, 0);
+
+    let magenta = original.replace(
+        "return vec4<f32>(color, surface.alpha);",
+        "return vec4<f32>(1.0, 0.0, 1.0, 1.0);",
+    );
+    assert_ne!(magenta, original, "Fragment shader output changed");
+    std::fs::write(&shader_path, magenta).unwrap();
+    assets.reload_files(
+        std::slice::from_ref(&shader_path),
+        &context.device,
+        &context.queue,
+    );
+
+    let center =
+        |image: &image::RgbaImage| image.get_pixel(common::WIDTH / 2, common::HEIGHT / 2).0;
+    let recolored = common::draw(&context, &mut pass, &entities, &camera);
+    assert_eq!(center(&recolored), [255, 0, 255, 255]);
+
+    // A broken shader leaves the last working pipeline in place
+    std::fs::write(&shader_path, original.clone() + "\nfn broken( {").unwrap();
+    assets.reload_files(
+        std::slice::from_ref(&shader_path),
+        &context.device,
+        &context.queue,
+    );
+
+    let after_error = common::draw(&context, &mut pass, &entities, &camera);
+    assert_eq!(common::compare(&after_error, &recolored, 0).mismatched, 0);
+
+    // Modules rebuild the shaders that include them
+    std::fs::write(&shader_path, original).unwrap();
+    let lighting_path = directory.join("lighting.wgsl");
+    let lighting = std::fs::read_to_string(&lighting_path).unwrap();
+    let unlit = lighting.replace("return PI * brdf(", "return 0.0 * brdf(");
+    assert_ne!(unlit, lighting, "Reflected light removed");
+    std::fs::write(&lighting_path, unlit).unwrap();
+    assets.reload_files(
+        &[shader_path, lighting_path.clone()],
+        &context.device,
+        &context.queue,
+    );
+
+    let unlit = common::draw(&context, &mut pass, &entities, &camera);
+    let brightness =
+        |image: &image::RgbaImage| center(image)[..3].iter().map(|&c| c as u32).sum::<u32>();
+    assert!(brightness(&unlit) < brightness(&builtin));
+
+    // And a module including one that doesn't exist keeps them as they were
+    std::fs::write(
+        &lighting_path,
+        "#include \"missing.wgsl\"\n".to_string() + &lighting,
+    )
+    .unwrap();
+    assets.reload_files(&[lighting_path], &context.device, &context.queue);
+
+    let after_error = common::draw(&context, &mut pass, &entities, &camera);
+    assert_eq!(common::compare(&after_error, &unlit, 0).mismatched, 0);
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,188 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use wgpu_renderer::{set_asset_roots, shader_directory_from_args, Assets, GraphicsContext};
+
+mod common;
+
+fn context() -> Option<GraphicsContext<'static>> {
+    common::context_with_size(1, 1)
+}
+
+/// Empty directory of its own for a test.
+fn scratch_dir(test: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("wgpu-renderer-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn write_image(path: &Path, size: u32) {
+    image::RgbaImage::from_pixel(size, size, image::Rgba([255, 0, 0, 255]))
+        .save(path)
+        .unwrap();
+}
+
+const TRIANGLE: &str = "mtllib model.mtl
+o triangle
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 0 1
+usemtl Painted
+f 1/1 2/2 3/3
+";
+
+const QUAD: &str = "mtllib model.mtl
+o quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+usemtl Painted
+f 1/1 2/2 3/3 4/4
+";
+
+const MATERIALS: &str = "newmtl Painted
+Kd 1 1 1
+map_Kd paint.png
+";
+
+/// Writes a model using a texture to `directory`, returning the path of the model.
+fn write_model(directory: &Path) -> PathBuf {
+    std::fs::write(directory.join("model.obj"), TRIANGLE).unwrap();
+    std::fs::write(directory.join("model.mtl"), MATERIALS).unwrap();
+    write_image(&directory.join("paint.png"), 2);
+    directory.join("model.obj")
+}
+
+#[test]
+fn changed_models_are_replaced_behind_their_handles() {
+    let Some(context) = context() else {
+        return;
+    };
+    let directory = scratch_dir("model-reload");
+    let path = write_model(&directory);
+    let assets = Assets::new();
+
+    let model = assets
+        .load_model(path.to_str().unwrap(), &context.device, &context.queue)
+        .unwrap();
+    let before = model.get();
+    assert_eq!(before.meshes[0].0.index_count, 3);
+
+    std::fs::write(&path, QUAD).unwrap();
+    assets.reload_files(&[path], &context.device, &context.queue);
+
+    assert_eq!(model.version(), 1);
+    assert_eq!(model.get().meshes[0].0.index_count, 6);
+    // Snapshots taken before the reload keep the previous version alive
+    assert_eq!(before.meshes[0].0.index_count, 3);
+    // The texture didn't change, so it's shared with the previous version
+    assert_eq!(
+        model.get().materials[0].texture,
+        before.materials[0].texture
+    );
+}
+
+#[test]
+fn changed_textures_are_replaced_in_the_models_using_them() {
+    let Some(context) = context() else {
+        return;
+    };
+    let directory = scratch_dir("texture-reload");
+    let path = write_model(&directory);
+    let assets = Assets::new();
+
+    let model = assets
+        .load_model(path.to_str().unwrap(), &context.device, &context.queue)
+        .unwrap();
+    let texture = model.get().materials[0].texture.clone();
+
+    write_image(&directory.join("paint.png"), 4);
+    assets.reload_files(
+        &[directory.join("paint.png")],
+        &context.device,
+        &context.queue,
+    );
+
+    assert_eq!(model.version(), 0);
+    assert_eq!(texture.version(), 1);
+    assert_eq!(texture.get().texture.width(), 4);
+}
+
+#[test]
+fn failed_reloads_keep_the_previous_asset() {
+    let Some(context) = context() else {
+        return;
+    };
+    let directory = scratch_dir("failed-reload");
+    let path = directory.join("paint.png");
+    write_image(&path, 2);
+    let assets = Assets::new();
+
+    let texture = assets
+        .load_texture(path.to_str().unwrap(), &context.device, &context.queue)
+        .unwrap();
+    let before = texture.get();
+
+    std::fs::write(&path, b"not an image").unwrap();
+    assets.reload_files(&[path], &context.device, &context.queue);
+
+    assert_eq!(texture.version(), 0);
+    assert!(std::sync::Arc::ptr_eq(&texture.get(), &before));
+}
+
+#[test]
+fn watched_files_are_reloaded_when_written() {
+    let Some(context) = context() else {
+        return;
+    };
+    let directory = scratch_dir("watch");
+    write_image(&directory.join("watched.png"), 2);
+    set_asset_roots([&directory]);
+
+    let assets = Assets::new();
+    let texture = assets
+        .load_texture("watched.png", &context.device, &context.queue)
+        .unwrap();
+    assets.watch().unwrap();
+
+    write_image(&directory.join("watched.png"), 4);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while texture.version() == 0 && Instant::now() < deadline {
+        assets.reload_changed(&context.device, &context.queue);
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    assert_eq!(texture.get().texture.width(), 4);
+}
+
+#[test]
+fn shader_directory_comes_from_the_command_line_flag() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(
+        shader_directory_from_args(args(&["--render-path", "deferred", "--shaders", "shaders"])),
+        Some(PathBuf::from("shaders"))
+    );
+    assert_eq!(
+        shader_directory_from_args(args(&["--shaders=/opt/shaders"])),
+        Some(PathBuf::from("/opt/shaders"))
+    );
+    // Cargo runs the tests from the crate, so its sources are the fallback
+    assert_eq!(
+        shader_directory_from_args(args(&["--fullscreen"])),
+        Some(Path::new(env!("CARGO_MANIFEST_DIR")).join("src/pass/shaders"))
+    );
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use wgpu_renderer::preprocess;
+
+/// Preprocesses `main.wgsl` out of `files`, with blank lines dropped to ease comparisons.
+fn expand(files: &[(&str, &str)], defines: &[&str]) -> anyhow::Result<String> {
+    let files = files.iter().copied().collect::<HashMap<_, _>>();
+    let output = preprocess("main.wgsl", defines, |name| {
+        files.get(name).map(|source| source.to_string())
+    })?;
+
+    Ok(output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+#[test]
+fn includes_are_pasted_once() {
+    let files = [
+        (
+            "main.wgsl",
+            "#include \"a.wgsl\"\n#include \"b.wgsl\"\nfn main() {}",
+        ),
+        ("a.wgsl", "#include \"common.wgsl\"\nfn a() {}"),
+        ("b.wgsl", "#include \"common.wgsl\"\nfn b() {}"),
+        ("common.wgsl", "struct Common { x: f32 };"),
+    ];
+
+    assert_eq!(
+        expand(&files, &[]).unwrap(),
+        "struct Common { x: f32 };\nfn a() {}\nfn b() {}\nfn main() {}"
+    );
+}
+
+#[test]
+fn conditions_keep_the_lines_of_defined_names() {
+    let files = [(
+        "main.wgsl",
+        "#ifdef SKINNED\nskinned\n#ifndef MORPH_TARGETS\nrigid\n#endif\n#else\nstatic\n#endif",
+    )];
+
+    assert_eq!(expand(&files, &[]).unwrap(), "static");
+    assert_eq!(expand(&files, &["SKINNED"]).unwrap(), "skinned\nrigid");
+    assert_eq!(
+        expand(&files, &["SKINNED", "MORPH_TARGETS"]).unwrap(),
+        "skinned"
+    );
+}
+
+#[test]
+fn defines_apply_to_the_rest_of_the_shader() {
+    let files = [
+        (
+            "main.wgsl",
+            "#include \"features.wgsl\"\n#ifdef SHADOWS\nshadows\n#endif",
+        ),
+        ("features.wgsl", "#ifdef LIT\n#define SHADOWS\n#endif"),
+    ];
+
+    assert_eq!(expand(&files, &[]).unwrap(), "");
+    assert_eq!(expand(&files, &["LIT"]).unwrap(), "shadows");
+}
+
+#[test]
+fn includes_in_dropped_lines_are_ignored() {
+    let files = [(
+        "main.wgsl",
+        "#ifdef MISSING\n#include \"missing.wgsl\"\n#endif\nfn main() {}",
+    )];
+
+    assert_eq!(expand(&files, &[]).unwrap(), "fn main() {}");
+}
+
+#[test]
+fn errors_point_at_the_directive() {
+    let error = |source| format!("{:#}", expand(&[("main.wgsl", source)], &[]).unwrap_err());
+
+    assert!(error("fn main() {}\n#include \"missing.wgsl\"")
+        .contains("main.wgsl:2: in the include of \"missing.wgsl\": Couldn't find WGSL module"));
+    assert!(error("#endif").contains("main.wgsl:1: #endif without #ifdef"));
+    assert!(error("#ifdef A\n#else\n#else\n#endif").contains("main.wgsl:3: #else without #ifdef"));
+    assert!(error("#ifdef A").contains("main.wgsl: #ifdef without #endif"));
+    assert!(error("#pragma once").contains("main.wgsl:1: unknown directive #pragma"));
+}
@@ -0,0 +1,135 @@
+use wgpu_renderer::ShaderInterface;
+
+const SHADER: &str = "
+struct Globals {
+    view_proj: mat4x4<f32>,
+};
+
+struct Lights {
+    count: u32,
+    items: array<vec4<f32>>,
+};
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+@group(0) @binding(1)
+var<storage, read> lights: Lights;
+@group(1) @binding(0)
+var tex_view: texture_2d<f32>;
+@group(1) @binding(1)
+var tex_sampler: sampler;
+@group(1) @binding(2)
+var shadow_map: texture_depth_2d_array;
+
+@vertex
+fn vs_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return globals.view_proj * vec4<f32>(position, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    let color = textureSample(tex_view, tex_sampler, vec2<f32>(0.5));
+    return color * f32(lights.count) + lights.items[0];
+}
+";
+
+/// Shader using a resource the one above declares without using it.
+const SHADOW_SHADER: &str = "
+@group(1) @binding(2)
+var shadow_map: texture_depth_2d_array;
+
+@vertex
+fn vs_main() -> @builtin(position) vec4<f32> {
+    return vec4<f32>(f32(textureNumLayers(shadow_map)));
+}
+";
+
+fn interface() -> ShaderInterface {
+    ShaderInterface::from_wgsl("test.wgsl", SHADER).unwrap()
+}
+
+#[test]
+fn layouts_follow_the_shader_declarations() {
+    let interface = interface();
+
+    let globals = interface.layout_entries(0);
+    assert_eq!(globals.len(), 2);
+    assert_eq!(globals[0].visibility, wgpu::ShaderStages::VERTEX);
+    assert_eq!(
+        globals[0].ty,
+        wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(64),
+        }
+    );
+    // Runtime sized arrays need room for one element
+    assert_eq!(
+        globals[1].ty,
+        wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(32),
+        }
+    );
+
+    let locals = interface.layout_entries(1);
+    assert_eq!(locals.len(), 3);
+    assert_eq!(locals[0].visibility, wgpu::ShaderStages::FRAGMENT);
+    assert_eq!(
+        locals[0].ty,
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        }
+    );
+    assert_eq!(
+        locals[1].ty,
+        wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+    );
+    // Declared but unused
+    assert_eq!(locals[2].visibility, wgpu::ShaderStages::NONE);
+}
+
+#[test]
+fn merged_shaders_see_the_bindings_they_share() {
+    let shadow = ShaderInterface::from_wgsl("shadow.wgsl", SHADOW_SHADER).unwrap();
+    let interface = interface().merge(shadow.group(1)).unwrap();
+
+    let locals = interface.layout_entries(1);
+    assert_eq!(locals[2].visibility, wgpu::ShaderStages::VERTEX);
+    interface.check_compatible(&shadow).unwrap();
+}
+
+#[test]
+fn sizes_are_checked_against_rust_structs() {
+    let interface = interface();
+
+    interface.check_size::<[[f32; 4]; 4]>("globals").unwrap();
+    let error = interface.check_size::<[f32; 4]>("globals").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "`[f32; 4]` is 16 bytes, but `globals` at @group(0) @binding(0) of test.wgsl is 64 bytes"
+    );
+    assert!(interface.check_size::<u32>("missing").is_err());
+}
+
+#[test]
+fn shaders_binding_other_resources_are_incompatible() {
+    let interface = interface().group(0);
+    let shadow = ShaderInterface::from_wgsl("shadow.wgsl", SHADOW_SHADER).unwrap();
+
+    let error = interface.check_compatible(&shadow).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "`shadow_map` at @group(1) @binding(2) of shadow.wgsl isn't bound by the renderer"
+    );
+
+    let resized = SHADER.replace(
+        "view_proj: mat4x4<f32>,",
+        "view_proj: mat4x4<f32>,\n    time: f32,",
+    );
+    let resized = ShaderInterface::from_wgsl("resized.wgsl", &resized).unwrap();
+    assert!(interface.check_compatible(&resized).is_err());
+}
@@ -0,0 +1,219 @@
+use wgpu_renderer::{GraphicsContext, RenderGraph, TextureDesc};
+
+mod common;
+
+fn context() -> Option<GraphicsContext<'static>> {
+    common::context_with_size(16, 16)
+}
+
+fn color() -> TextureDesc {
+    TextureDesc::frame_sized(wgpu::TextureFormat::Rgba8Unorm)
+}
+
+fn clear(encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, color: wgpu::Color) {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(color),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+}
+
+#[test]
+fn nodes_run_after_the_writers_of_what_they_read() {
+    let Some(context) = context() else {
+        return;
+    };
+    let frame = context.target.current_frame();
+    let mut graph = RenderGraph::new();
+    let mut frame_graph = graph.begin(&frame);
+
+    let target = frame_graph.target();
+    let lit = frame_graph.create_texture("Lit", color());
+    frame_graph
+        .add_node("Composite")
+        .read(lit)
+        .write(target)
+        .record(|_, _| {});
+    frame_graph
+        .add_node("Lighting")
+        .write(lit)
+        .record(|_, _| {});
+    frame_graph
+        .add_node("Overlay")
+        .write(target)
+        .record(|_, _| {});
+
+    assert_eq!(
+        frame_graph.schedule().unwrap(),
+        ["Lighting", "Composite", "Overlay"]
+    );
+}
+
+#[test]
+fn nodes_nothing_uses_are_skipped() {
+    let Some(context) = context() else {
+        return;
+    };
+    let frame = context.target.current_frame();
+    let mut graph = RenderGraph::new();
+    let mut frame_graph = graph.begin(&frame);
+
+    let target = frame_graph.target();
+    let unused = frame_graph.create_texture("Unused", color());
+    frame_graph
+        .add_node("Debug view")
+        .write(unused)
+        .record(|_, _| {
+            panic!("Skipped nodes aren't recorded");
+        });
+    frame_graph.add_node("Main").write(target).record(|_, _| {});
+
+    assert_eq!(frame_graph.schedule().unwrap(), ["Main"]);
+    frame_graph
+        .execute(&context.device, &context.queue)
+        .unwrap();
+    assert_eq!(graph.transient_textures(), 0);
+}
+
+#[test]
+fn invalid_graphs_are_errors() {
+    let Some(context) = context() else {
+        return;
+    };
+    let frame = context.target.current_frame();
+    let mut graph = RenderGraph::new();
+
+    let mut frame_graph = graph.begin(&frame);
+    let target = frame_graph.target();
+    let never_written = frame_graph.create_texture("G-buffer", color());
+    frame_graph
+        .add_node("Lighting")
+        .read(never_written)
+        .write(target)
+        .record(|_, _| {});
+    assert_eq!(
+        frame_graph.schedule().unwrap_err().to_string(),
+        "`Lighting` reads `G-buffer`, which no node writes"
+    );
+    drop(frame_graph);
+
+    let mut frame_graph = graph.begin(&frame);
+    let target = frame_graph.target();
+    let a = frame_graph.create_texture("A", color());
+    let b = frame_graph.create_texture("B", color());
+    frame_graph
+        .add_node("Ping")
+        .read(a)
+        .write(b)
+        .record(|_, _| {});
+    frame_graph
+        .add_node("Pong")
+        .read(b)
+        .write(a)
+        .record(|_, _| {});
+    frame_graph
+        .add_node("Main")
+        .read(a)
+        .write(target)
+        .record(|_, _| {});
+    assert_eq!(
+        frame_graph.schedule().unwrap_err().to_string(),
+        "The nodes `Ping`, `Pong`, `Main` depend on each other"
+    );
+}
+
+#[test]
+fn transients_share_textures_once_they_are_done() {
+    let Some(context) = context() else {
+        return;
+    };
+    let GraphicsContext {
+        device,
+        queue,
+        target,
+        ..
+    } = &context;
+    let mut graph = RenderGraph::new();
+
+    for _ in 0..2 {
+        let frame = target.current_frame();
+        let mut frame_graph = graph.begin(&frame);
+
+        // The first texture is done by the time the third one is written
+        let target = frame_graph.target();
+        let first = frame_graph.create_texture("First", color());
+        let second = frame_graph.create_texture("Second", color());
+        let third = frame_graph.create_texture("Third", color());
+        let chain = [(first, second), (second, third)];
+
+        frame_graph
+            .add_node("Start")
+            .write(first)
+            .record(move |encoder, resources| {
+                clear(encoder, resources.view(first), wgpu::Color::RED)
+            });
+        for (read, write) in chain {
+            frame_graph.add_node("Step").read(read).write(write).record(
+                move |encoder, resources| clear(encoder, resources.view(write), wgpu::Color::GREEN),
+            );
+        }
+        frame_graph
+            .add_node("End")
+            .read(third)
+            .write(target)
+            .record(move |encoder, resources| {
+                clear(encoder, resources.view(target), wgpu::Color::BLUE)
+            });
+
+        frame_graph.execute(device, queue).unwrap();
+        assert_eq!(graph.transient_textures(), 2);
+
+        let image = frame.capture(device, queue).unwrap();
+        assert_eq!(image.get_pixel(8, 8).0, [0, 0, 255, 255]);
+        frame.present();
+    }
+}
+
+#[test]
+fn buffers_order_the_nodes_using_them() {
+    let Some(context) = context() else {
+        return;
+    };
+    let frame = context.target.current_frame();
+    let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Light clusters"),
+        size: 16,
+        usage: wgpu::BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    });
+    let mut graph = RenderGraph::new();
+    let mut frame_graph = graph.begin(&frame);
+
+    let target = frame_graph.target();
+    let clusters = frame_graph.import_buffer("Light clusters", &buffer);
+    frame_graph
+        .add_node("Shading")
+        .read(clusters)
+        .write(target)
+        .record(|_, _| {});
+    frame_graph
+        .add_node("Culling")
+        .write(clusters)
+        .record(|_, resources| {
+            assert_eq!(resources.buffer(clusters).size(), 16);
+        });
+
+    assert_eq!(frame_graph.schedule().unwrap(), ["Culling", "Shading"]);
+    frame_graph
+        .execute(&context.device, &context.queue)
+        .unwrap();
+}
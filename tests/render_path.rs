@@ -0,0 +1,26 @@
+use wgpu_renderer::{render_path_from_args, RenderPath};
+
+#[test]
+fn render_path_comes_from_the_command_line_flag() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(
+        render_path_from_args(args(&["--assets", "data", "--render-path", "deferred"])).unwrap(),
+        Some(RenderPath::Deferred)
+    );
+    assert_eq!(
+        render_path_from_args(args(&["--render-path=forward"])).unwrap(),
+        Some(RenderPath::Forward)
+    );
+    assert_eq!(
+        render_path_from_args(args(&["--fullscreen"])).unwrap(),
+        None
+    );
+    assert_eq!(
+        render_path_from_args(args(&["--render-path", "raytraced"]))
+            .unwrap_err()
+            .to_string(),
+        "Unknown render path `raytraced`, expected forward, deferred or clustered"
+    );
+    assert!(render_path_from_args(args(&["--render-path"])).is_err());
+}